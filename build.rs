@@ -1,8 +1,9 @@
 extern crate gl_generator;
 
-use gl_generator::{Registry, Api, Profile, Fallbacks, GlobalGenerator};
+use gl_generator::{Registry, Api, Profile, Fallbacks, GlobalGenerator, Generator};
 use std::env;
 use std::fs::File;
+use std::io;
 use std::path::Path;
 
 
@@ -10,7 +11,68 @@ fn main() {
     let dest = env::var("OUT_DIR").unwrap();
     let mut file = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
 
-    Registry::new(Api::Gles2, (2, 0), Profile::Core, Fallbacks::All, [])
-        .write_bindings(GlobalGenerator, &mut file)
-        .unwrap();
+    let registry = Registry::new(Api::Gles2, (3, 0), Profile::Core, Fallbacks::All, ["GL_EXT_disjoint_timer_query", "GL_KHR_debug", "GL_EXT_robustness"]);
+
+    if env::var("CARGO_FEATURE_GL_TRACE").is_ok() {
+        registry.write_bindings(TracingGenerator, &mut file).unwrap();
+    } else {
+        registry.write_bindings(GlobalGenerator, &mut file).unwrap();
+    }
+}
+
+
+//The normal global bindings moved into a `raw` module, with every function
+//shadowed by one that calls it and passes the call to gl_trace::record().
+struct TracingGenerator;
+
+impl Generator for TracingGenerator {
+    fn write<W: io::Write>(&self, registry: &Registry, dest: &mut W) -> io::Result<()> {
+        writeln!(dest, "pub(crate) mod raw {{")?;
+        GlobalGenerator.write(registry, dest)?;
+        writeln!(dest, "}}")?;
+        writeln!(dest, "pub use self::raw::*;")?;
+        writeln!(dest, "mod __gl_imports {{ pub use std::os::raw; }}")?;
+
+        for cmd in registry.cmds.iter() {
+            let name = &cmd.proto.ident;
+            let params: Vec<String> = cmd.params.iter().map(|p| format!("{}: {}", p.ident, p.ty)).collect();
+            let idents: Vec<&str> = cmd.params.iter().map(|p| &p.ident[..]).collect();
+
+            //GetError hands back what a traced call already took from gl
+            if name == "GetError" {
+                writeln!(dest,
+                    "#[allow(non_snake_case, dead_code)] #[inline] pub unsafe fn GetError() -> {} {{ crate::gl_trace::take_error().unwrap_or_else(|| raw::GetError()) }}",
+                    cmd.proto.ty)?;
+                continue;
+            }
+
+            //pointers only say whether they are null, so captures of two runs can be diffed
+            let fmt: Vec<&str> = cmd.params.iter()
+                .map(|p| if p.ty == "types::GLenum" { "{:#x}" } else if p.ty.starts_with('*') { "{}" } else { "{:?}" })
+                .collect();
+            let args: Vec<String> = cmd.params.iter()
+                .map(|p| if p.ty.starts_with('*') { null_or_ptr(&p.ident) } else { p.ident.clone() })
+                .collect();
+            let result = if cmd.proto.ty.starts_with('*') { null_or_ptr("__result") } else { "__result".to_string() };
+            writeln!(dest,
+                "#[allow(non_snake_case, dead_code)] #[inline] pub unsafe fn {name}({params}) -> {ret} {{ \
+                    let __result = raw::{name}({idents}); \
+                    crate::gl_trace::record(\"gl{name}\", format_args!(\"{fmt}\"{comma}{args}), &{result}); \
+                    __result \
+                }}",
+                name = name,
+                params = params.join(", "),
+                ret = cmd.proto.ty,
+                idents = idents.join(", "),
+                fmt = fmt.join(", "),
+                args = args.join(", "),
+                result = result,
+                comma = if cmd.params.is_empty() { "" } else { ", " })?;
+        }
+        Ok(())
+    }
+}
+
+fn null_or_ptr(ident: &str) -> String {
+    format!("if {}.is_null() {{ \"null\" }} else {{ \"ptr\" }}", ident)
 }
@@ -0,0 +1,83 @@
+use crate::GlSys;
+use glutin::event::{Event,WindowEvent,KeyboardInput,ElementState,VirtualKeyCode};
+use glutin::event_loop::{EventLoop,ControlFlow};
+
+use std::time::Instant;
+
+//A frame that took longer than this many timesteps drops the rest,
+//so slow updates can't make every following frame slower.
+const MAX_UPDATES:usize=8;
+
+
+///What run() drives.
+pub trait App{
+    ///Advances the simulation by one fixed timestep of dt seconds.
+    fn update(&mut self,dt:f32);
+
+    ///Draws a frame. alpha from 0 to 1 is how far the frame is from the
+    ///previous update to the next one, for interpolating positions.
+    ///The buffers are swapped after it returns.
+    fn render(&mut self,sys:&mut GlSys,alpha:f32);
+
+    ///Sees every event before run() handles it.
+    fn handle_event(&mut self,_sys:&mut GlSys,_event:&Event<()>){}
+
+    ///Called after run() recovered from a lost context,
+    ///to create textures and fonts again. See GlSys::recover().
+    fn context_recovered(&mut self,_sys:&mut GlSys){}
+}
+
+
+///Runs the event loop until the window is closed or escape is pressed.
+///
+///update() is called once every timestep seconds, several times in a frame
+///if it has to catch up. Then render() is called once and the buffers swapped.
+pub fn run<A:App+'static>(events_loop:EventLoop<()>,mut sys:GlSys,mut app:A,timestep:f32)->!{
+    assert!(timestep>0.0);
+    let mut last=Instant::now();
+    let mut accumulator=0.0;
+
+    events_loop.run(move |event,target,control_flow|{
+        if *control_flow!=ControlFlow::Exit{
+            *control_flow=ControlFlow::Poll;
+        }
+        app.handle_event(&mut sys,&event);
+
+        match &event{
+            Event::WindowEvent{event,..}=>match event{
+                WindowEvent::CloseRequested=>*control_flow=ControlFlow::Exit,
+                WindowEvent::Resized(size)=>sys.resize(*size),
+                WindowEvent::KeyboardInput{input:KeyboardInput{virtual_keycode:Some(VirtualKeyCode::Escape),state:ElementState::Pressed,..},..}=>{
+                    *control_flow=ControlFlow::Exit
+                },
+                _=>{}
+            },
+            Event::EventsCleared=>{
+                if sys.is_context_lost(){
+                    sys.recover(target);
+                    app.context_recovered(&mut sys);
+                }
+
+                let now=Instant::now();
+                accumulator+=(now-last).as_secs_f32();
+                last=now;
+
+                let mut updates=0;
+                while accumulator>=timestep{
+                    if updates==MAX_UPDATES{
+                        log::debug!(target:"gl::app",dropped_ms=accumulator*1000.0;"dropping updates");
+                        accumulator%=timestep;
+                        break;
+                    }
+                    app.update(timestep);
+                    accumulator-=timestep;
+                    updates+=1;
+                }
+
+                app.render(&mut sys,accumulator/timestep);
+                sys.swap_buffers();
+            },
+            _=>{}
+        }
+    })
+}
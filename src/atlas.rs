@@ -0,0 +1,212 @@
+use axgeom::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+use crate::context::{self,ContextId,Object};
+use crate::texture::*;
+
+
+
+
+///Packs many RGBA images into one texture.
+///
+///Images are placed on shelves sorted by height,
+///with a transparent gap between them so filtering doesn't bleed.
+pub struct AtlasBuilder{
+    max_width:usize,
+    padding:usize,
+    images:Vec<(Vec2<usize>,Vec<u8>)>,
+}
+
+impl AtlasBuilder{
+    ///No shelf gets wider than max_width, so no image may be wider either.
+    pub fn new(max_width:usize)->AtlasBuilder{
+        AtlasBuilder{max_width,padding:1,images:Vec::new()}
+    }
+
+    ///The gap in texels between images, defaults to one.
+    pub fn with_padding(mut self,padding:usize)->AtlasBuilder{
+        self.padding=padding;
+        self
+    }
+
+    ///Returns the index of the sprite, to be used in SpriteVertex.
+    ///data holds dim.x*dim.y RGBA texels, top row first.
+    pub fn add(&mut self,dim:Vec2<usize>,data:&[u8])->u32{
+        assert_eq!(data.len(),dim.x*dim.y*4);
+        assert!(dim.x+2*self.padding<=self.max_width,"image wider than the atlas");
+        self.images.push((dim,data.to_vec()));
+        (self.images.len()-1) as u32
+    }
+
+    pub fn build(self,options:TextureOptions)->SpriteAtlas{
+        let dims:Vec<Vec2<usize>>=self.images.iter().map(|(d,_)|*d).collect();
+        let (positions,dim)=pack(&dims,self.max_width,self.padding);
+
+        let mut data=vec![0u8;dim.x*dim.y*4];
+        for ((idim,idata),pos) in self.images.iter().zip(positions.iter()){
+            for row in 0..idim.y{
+                let src=&idata[row*idim.x*4..(row+1)*idim.x*4];
+                let start=((pos.y+row)*dim.x+pos.x)*4;
+                data[start..start+idim.x*4].copy_from_slice(src);
+            }
+        }
+
+        let rects:Vec<[f32;4]>=self.images.iter().zip(positions.iter()).map(|((idim,_),pos)|{
+            [
+                pos.x as f32/dim.x as f32,
+                pos.y as f32/dim.y as f32,
+                idim.x as f32/dim.x as f32,
+                idim.y as f32/dim.y as f32
+            ]
+        }).collect();
+
+        let texture=Texture2D::from_rgba(dim,&data,options);
+        let rect_texture=create_rect_texture(&rects);
+        SpriteAtlas{texture,rects,rect_texture,ctx:context::current()}
+    }
+}
+
+
+//Shelf packs images of the given sizes. Returns the top left corner
+//of each and the size of the whole atlas.
+fn pack(dims:&[Vec2<usize>],max_width:usize,pad:usize)->(Vec<Vec2<usize>>,Vec2<usize>){
+    //place the tallest first so shelves waste less space
+    let mut order:Vec<usize>=(0..dims.len()).collect();
+    order.sort_by_key(|&i|std::cmp::Reverse(dims[i].y));
+
+    let mut positions=vec![vec2(0,0);dims.len()];
+    let mut x=pad;
+    let mut y=pad;
+    let mut shelf_height=0;
+    for &i in order.iter(){
+        let dim=dims[i];
+        if x+dim.x+pad>max_width{
+            x=pad;
+            y+=shelf_height+pad;
+            shelf_height=0;
+        }
+        positions[i]=vec2(x,y);
+        x+=dim.x+pad;
+        shelf_height=shelf_height.max(dim.y);
+    }
+    (positions,vec2(max_width,(y+shelf_height+pad).max(1)))
+}
+
+
+///The number of rects per row of the rect texture.
+pub(crate) const RECTS_PER_ROW:usize=256;
+
+//The uv rects stored in an RGBA32F texture so shaders can look them up by index.
+fn create_rect_texture(rects:&[[f32;4]])->GLuint{
+    let rows=rects.len().div_ceil(RECTS_PER_ROW);
+    let rows=rows.max(1);
+    let mut data=vec![0.0f32;RECTS_PER_ROW*rows*4];
+    for (i,r) in rects.iter().enumerate(){
+        data[i*4..i*4+4].copy_from_slice(r);
+    }
+
+    let mut texture=0;
+    unsafe{
+        gl::GenTextures(1,&mut texture);
+        gl::BindTexture(gl::TEXTURE_2D,texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA32F as GLint,
+            RECTS_PER_ROW as GLsizei,
+            rows as GLsizei,
+            0,
+            gl::RGBA,
+            gl::FLOAT,
+            data.as_ptr() as *const std::ffi::c_void
+        );
+        //float textures can't be filtered
+        gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MIN_FILTER,gl::NEAREST as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MAG_FILTER,gl::NEAREST as GLint);
+    }
+    debug::check_error();
+    texture
+}
+
+
+///A texture with many sprites in it, built with AtlasBuilder.
+pub struct SpriteAtlas{
+    texture:Texture2D,
+    rects:Vec<[f32;4]>,
+    rect_texture:GLuint,
+    ctx:ContextId,
+}
+
+impl SpriteAtlas{
+    pub fn get_texture(&self)->&Texture2D{
+        &self.texture
+    }
+
+    ///The uv rect (x,y,width,height) of the sprite, with v=0 at the top row.
+    pub fn get_rect(&self,sprite:u32)->[f32;4]{
+        self.rects[sprite as usize]
+    }
+
+    pub fn get_num_sprites(&self)->usize{
+        self.rects.len()
+    }
+
+    pub(crate) fn get_rect_texture_id(&self)->GLuint{
+        self.rect_texture
+    }
+}
+
+impl Drop for SpriteAtlas{
+    fn drop(&mut self){
+        context::delete(self.ctx,&[Object::Texture(self.rect_texture)]);
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn check(dims:&[Vec2<usize>],max_width:usize,pad:usize){
+        let (positions,atlas)=pack(dims,max_width,pad);
+        assert_eq!(positions.len(),dims.len());
+        assert_eq!(atlas.x,max_width);
+
+        let rects:Vec<(usize,usize,usize,usize)>=positions.iter().zip(dims.iter()).map(|(p,d)|(p.x,p.y,p.x+d.x,p.y+d.y)).collect();
+        for (i,a) in rects.iter().enumerate(){
+            assert!(a.0>=pad && a.1>=pad && a.2+pad<=atlas.x && a.3+pad<=atlas.y,"{:?} outside {:?}",a,atlas);
+            for b in rects[i+1..].iter(){
+                //padding apart on at least one axis
+                let apart=a.2+pad<=b.0 || b.2+pad<=a.0 || a.3+pad<=b.1 || b.3+pad<=a.1;
+                assert!(apart,"{:?} overlaps {:?}",a,b);
+            }
+        }
+    }
+
+    #[test]
+    fn packed_rects_dont_overlap(){
+        let mut dims=Vec::new();
+        //a fixed pseudo random mix of sizes
+        let mut seed=12345u32;
+        for _ in 0..200{
+            seed=seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let w=1+(seed>>16) as usize%60;
+            seed=seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let h=1+(seed>>16) as usize%60;
+            dims.push(vec2(w,h));
+        }
+        check(&dims,256,1);
+        check(&dims,128,0);
+        check(&dims,64,2);
+    }
+
+    #[test]
+    fn edge_cases(){
+        check(&[],64,1);
+        check(&[vec2(62,10)],64,1);
+        check(&[vec2(32,32);4],64,0);
+        let (_,atlas)=pack(&[],64,0);
+        assert_eq!(atlas,vec2(64,1));
+    }
+}
@@ -0,0 +1,37 @@
+use crate::gl;
+use crate::state;
+
+
+
+
+///How a draw call is combined with what is already in the framebuffer.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Default)]
+pub enum BlendMode{
+    ///Normal transparency with straight (not premultiplied) alpha.
+    #[default]
+    Alpha,
+    ///Transparency where the color has already been multiplied by alpha.
+    PremultipliedAlpha,
+    ///Adds the color scaled by alpha, for glowing things.
+    Additive,
+    ///Multiplies with the destination. Darkens, alpha is ignored.
+    Multiply,
+    ///The inverse of multiply. Lightens, alpha is ignored.
+    Screen,
+    ///Overwrites the destination.
+    None,
+}
+
+impl BlendMode{
+    pub(crate) fn apply(self){
+        let func=match self{
+            BlendMode::Alpha=>Some((gl::SRC_ALPHA,gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::PremultipliedAlpha=>Some((gl::ONE,gl::ONE_MINUS_SRC_ALPHA)),
+            BlendMode::Additive=>Some((gl::SRC_ALPHA,gl::ONE)),
+            BlendMode::Multiply=>Some((gl::DST_COLOR,gl::ZERO)),
+            BlendMode::Screen=>Some((gl::ONE,gl::ONE_MINUS_SRC_COLOR)),
+            BlendMode::None=>None,
+        };
+        state::set_blend(func);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::shader::*;
+use crate::gl;
+use crate::debug;
+
+use crate::offscreen::OffscreenTarget;
+use crate::program_cache::ProgramCache;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::stats;
+use crate::state;
+use crate::postprocess::Uniform;
+
+
+//A quad covering the whole gl viewport generated from gl_VertexID,
+//so no vertex buffer is needed. Draw it as a 4 vertex triangle strip.
+pub(crate) static FULLSCREEN_VS_SRC: &'static str = "
+#version 300 es
+out vec2 uv;
+void main() {
+    vec2 p = vec2(float(gl_VertexID & 1), float(gl_VertexID >> 1));
+    uv = p;
+    gl_Position = vec4(p * 2.0 - 1.0, 0.0, 1.0);
+}";
+
+static FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform sampler2D tex;
+uniform float alpha;
+uniform bool premultiplied;
+out vec4 out_color;
+void main() {
+    vec4 c = texture(tex, uv);
+    if (premultiplied) {
+        out_color = c * alpha;
+    } else {
+        out_color = vec4(c.rgb, c.a * alpha);
+    }
+}";
+
+
+
+
+///Draws the texture of an OffscreenTarget into a viewport.
+pub struct BlitProgram{
+    program:Program,
+}
+
+impl BlitProgram{
+    pub fn new()->BlitProgram{
+        Self::with_cache(None)
+    }
+
+    ///Same as new() but tries to load the linked program from the cache first.
+    pub fn new_cached(cache:&ProgramCache)->BlitProgram{
+        Self::with_cache(Some(cache))
+    }
+
+    fn with_cache(cache:Option<&ProgramCache>)->BlitProgram{
+        BlitProgram{program:Program::new("BlitProgram",FULLSCREEN_VS_SRC,FS_SRC,cache)}
+    }
+
+    ///Stretches the whole target over the area inside the viewport's border.
+    ///Multisampled targets are resolved first.
+    pub fn draw(&mut self,viewport:&Viewport,target:&OffscreenTarget,alpha:f32,blend:BlendMode){
+        self.program.restore();
+        let program=self.program.get_id();
+        target.resolve();
+        viewport.begin_inner();
+        blend.apply();
+        unsafe{
+            state::use_program(program);
+
+            state::set_uniform(program,"premultiplied",Uniform::Int(if blend==BlendMode::PremultipliedAlpha{1}else{0}));
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D,target.get_texture_id());
+
+            state::set_uniform(program,"tex",Uniform::Int(0));
+
+            state::set_uniform(program,"alpha",Uniform::Float(alpha));
+            debug::check_error();
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP,0,4);
+            stats::draw_call(4);
+            debug::check_error();
+        }
+        Viewport::end();
+    }
+}
@@ -0,0 +1,328 @@
+use axgeom::*;
+
+
+
+///How the camera viewport is fit into a window with a different aspect ratio.
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum FitMode{
+    ///Scale x and y independently so the viewport fills the window exactly.
+    ///World units are not square.
+    Stretch,
+    ///Show all of the viewport and fill the rest of the window with bars
+    ///of the given color.
+    Letterbox([f32;3]),
+    ///Fill the window, cutting off the parts of the viewport that don't fit.
+    Crop,
+    ///Fill the window, showing more world than the viewport along one axis.
+    Expand,
+}
+
+
+///The result of fitting a camera into a window of a particular size.
+#[derive(Copy,Clone,Debug)]
+pub struct View{
+    ///Top left corner of the drawn area in pixels.
+    pub offset:Vec2<f32>,
+    ///Size of the drawn area in pixels.
+    pub size:Vec2<f32>,
+    ///Maps the world to normalized device coordinates of the drawn area.
+    ///Column major, ready to be uploaded with glUniformMatrix3fv.
+    pub matrix:[[f32;3];3],
+    ///Pixels per world unit along each axis.
+    ///They are equal for every fit mode except stretch.
+    pub scale:Vec2<f32>,
+    ///The rotation of the camera.
+    pub rotation:f32,
+}
+
+
+impl View{
+    ///Converts a position in physical window pixels to world coordinates.
+    ///The window origin is at the top left, as with glutin cursor events.
+    pub fn screen_to_world(&self,pos:Vec2<f32>)->Vec2<f32>{
+        let nx=(pos.x-self.offset.x)/self.size.x*2.0-1.0;
+        let ny=1.0-(pos.y-self.offset.y)/self.size.y*2.0;
+
+        let m=&self.matrix;
+        let det=m[0][0]*m[1][1]-m[1][0]*m[0][1];
+        let dx=nx-m[2][0];
+        let dy=ny-m[2][1];
+        vec2(
+            (m[1][1]*dx-m[1][0]*dy)/det,
+            (m[0][0]*dy-m[0][1]*dx)/det
+        )
+    }
+
+    ///Converts a world position to physical window pixels.
+    pub fn world_to_screen(&self,pos:Vec2<f32>)->Vec2<f32>{
+        let m=&self.matrix;
+        let nx=m[0][0]*pos.x+m[1][0]*pos.y+m[2][0];
+        let ny=m[0][1]*pos.x+m[1][1]*pos.y+m[2][1];
+        vec2(
+            self.offset.x+(nx+1.0)/2.0*self.size.x,
+            self.offset.y+(1.0-ny)/2.0*self.size.y
+        )
+    }
+
+    ///Same as screen_to_world() but for logical pixels.
+    pub fn logical_to_world(&self,pos:Vec2<f32>,hidpi_factor:f32)->Vec2<f32>{
+        self.screen_to_world(vec2(pos.x*hidpi_factor,pos.y*hidpi_factor))
+    }
+
+    ///Same as world_to_screen() but for logical pixels.
+    pub fn world_to_logical(&self,pos:Vec2<f32>,hidpi_factor:f32)->Vec2<f32>{
+        let p=self.world_to_screen(pos);
+        vec2(p.x/hidpi_factor,p.y/hidpi_factor)
+    }
+}
+
+
+///A 2d camera describing which part of the world is shown.
+///
+///Uses the same orientation as the rest of the crate:
+///the y axis points down, so at zero rotation the smallest y
+///is at the top of the screen.
+#[derive(Copy,Clone,Debug)]
+pub struct Camera2D{
+    ///The world position that appears in the middle of the view.
+    pub center:Vec2<f32>,
+    ///1.0 shows exactly `viewport` worth of world. 2.0 shows half as much.
+    pub zoom:f32,
+    ///In radians. Rotates the view around `center`.
+    pub rotation:f32,
+    ///The width and height of the world visible at a zoom of 1.0.
+    pub viewport:Vec2<f32>,
+    ///Defaults to stretch.
+    pub fit:FitMode,
+}
+
+impl Camera2D{
+    pub fn new(center:Vec2<f32>,viewport:Vec2<f32>)->Camera2D{
+        Camera2D{center,zoom:1.0,rotation:0.0,viewport,fit:FitMode::Stretch}
+    }
+
+    ///A camera that shows exactly the given world rectangle.
+    pub fn from_rect(rect:Rect<f32>)->Camera2D{
+        let ((x1,x2),(y1,y2))=rect.get();
+        Camera2D::new(vec2((x1+x2)/2.0,(y1+y2)/2.0),vec2(x2-x1,y2-y1))
+    }
+
+    ///The width and height of the world currently visible.
+    pub fn visible_size(&self)->Vec2<f32>{
+        vec2(self.viewport.x/self.zoom,self.viewport.y/self.zoom)
+    }
+
+    pub fn pan(&mut self,delta:Vec2<f32>){
+        self.center.x+=delta.x;
+        self.center.y+=delta.y;
+    }
+
+    ///Multiplies the current zoom. Values above one zoom in.
+    pub fn zoom_by(&mut self,factor:f32){
+        self.zoom*=factor;
+    }
+
+    pub fn rotate(&mut self,angle:f32){
+        self.rotation+=angle;
+    }
+
+    ///Moves the center towards target, framerate-independently.
+    ///Higher smoothing catches up faster.
+    pub fn follow(&mut self,target:Vec2<f32>,smoothing:f32,dt:f32){
+        let t=1.0-(-smoothing*dt).exp();
+        self.center.x+=(target.x-self.center.x)*t;
+        self.center.y+=(target.y-self.center.y)*t;
+    }
+
+    ///Centers on the rectangle and picks the largest zoom that still
+    ///shows all of it at the current rotation.
+    pub fn zoom_to_fit(&mut self,rect:Rect<f32>){
+        let ((x1,x2),(y1,y2))=rect.get();
+        let (w,h)=(x2-x1,y2-y1);
+        let (sin,cos)=self.rotation.sin_cos();
+        let (sin,cos)=(sin.abs(),cos.abs());
+
+        //bounding box of the rect in view space
+        let bw=w*cos+h*sin;
+        let bh=w*sin+h*cos;
+
+        self.center=vec2((x1+x2)/2.0,(y1+y2)/2.0);
+        self.zoom=(self.viewport.x/bw).min(self.viewport.y/bh);
+    }
+
+    ///Fits the camera into a view of the given pixel dimensions.
+    pub fn view(&self,dim:Vec2<usize>)->View{
+        let (w,h)=(dim.x as f32,dim.y as f32);
+        let visible=self.visible_size();
+        let fit_scale=(w/visible.x).min(h/visible.y);
+        let fill_scale=(w/visible.x).max(h/visible.y);
+
+        let (offset,size,visible)=match self.fit{
+            FitMode::Stretch=>{
+                (vec2(0.0,0.0),vec2(w,h),visible)
+            },
+            FitMode::Letterbox(_)=>{
+                let size=vec2(visible.x*fit_scale,visible.y*fit_scale);
+                (vec2((w-size.x)/2.0,(h-size.y)/2.0),size,visible)
+            },
+            FitMode::Crop=>{
+                (vec2(0.0,0.0),vec2(w,h),vec2(w/fill_scale,h/fill_scale))
+            },
+            FitMode::Expand=>{
+                (vec2(0.0,0.0),vec2(w,h),vec2(w/fit_scale,h/fit_scale))
+            }
+        };
+
+        View{
+            offset,
+            size,
+            matrix:self.matrix(visible),
+            scale:vec2(size.x/visible.x,size.y/visible.y),
+            rotation:self.rotation
+        }
+    }
+
+    ///Shorthand for view(dim).screen_to_world(pos).
+    pub fn screen_to_world(&self,dim:Vec2<usize>,pos:Vec2<f32>)->Vec2<f32>{
+        self.view(dim).screen_to_world(pos)
+    }
+
+    ///Shorthand for view(dim).world_to_screen(pos).
+    pub fn world_to_screen(&self,dim:Vec2<usize>,pos:Vec2<f32>)->Vec2<f32>{
+        self.view(dim).world_to_screen(pos)
+    }
+
+    //Maps a visible area of the given size around the center to
+    //normalized device coordinates.
+    fn matrix(&self,visible:Vec2<f32>)->[[f32;3];3]{
+        let sx=2.0/visible.x;
+        let sy=2.0/visible.y;
+        let (sin,cos)=self.rotation.sin_cos();
+        let Vec2{x:cx,y:cy}=self.center;
+
+        //translate by -center, rotate by -rotation, scale, flip y.
+        let tx=-sx*(cos*cx+sin*cy);
+        let ty=sy*(cos*cy-sin*cx);
+        [
+            [sx*cos, sy*sin, 0.0],
+            [sx*sin, -sy*cos, 0.0],
+            [tx, ty, 1.0]
+        ]
+    }
+}
+
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn assert_close(a:Vec2<f32>,b:Vec2<f32>){
+        assert!((a.x-b.x).abs()<1e-2 && (a.y-b.y).abs()<1e-2,"{:?} != {:?}",a,b);
+    }
+
+    fn cameras()->Vec<Camera2D>{
+        let fits=[FitMode::Stretch,FitMode::Letterbox([0.0;3]),FitMode::Crop,FitMode::Expand];
+        let rotations=[0.0,0.7,std::f32::consts::FRAC_PI_2,-2.3];
+        let mut cameras=Vec::new();
+        for &fit in fits.iter(){
+            for &rotation in rotations.iter(){
+                let mut c=Camera2D::new(vec2(3.0,-5.0),vec2(40.0,30.0));
+                c.zoom=1.5;
+                c.rotation=rotation;
+                c.fit=fit;
+                cameras.push(c);
+            }
+        }
+        cameras
+    }
+
+    #[test]
+    fn screen_world_round_trip(){
+        for camera in cameras(){
+            for &dim in [vec2(800,600),vec2(600,900)].iter(){
+                let view=camera.view(dim);
+                for &p in [vec2(0.0,0.0),vec2(400.0,300.0),vec2(123.0,456.0),vec2(599.0,10.0)].iter(){
+                    assert_close(view.world_to_screen(view.screen_to_world(p)),p);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn world_screen_round_trip(){
+        for camera in cameras(){
+            let view=camera.view(vec2(800,600));
+            for &p in [vec2(3.0,-5.0),vec2(0.0,0.0),vec2(-12.5,7.25)].iter(){
+                assert_close(view.screen_to_world(view.world_to_screen(p)),p);
+            }
+        }
+    }
+
+    //Shows world x from 0 to 40 and y from 0 to 30, a 4:3 area.
+    fn rect_camera(fit:FitMode)->Camera2D{
+        let mut c=Camera2D::from_rect(Rect::new(0.0,40.0,0.0,30.0));
+        c.fit=fit;
+        c
+    }
+
+    #[test]
+    fn corners_at_zero_rotation(){
+        let view=rect_camera(FitMode::Stretch).view(vec2(800,600));
+        assert_close(view.screen_to_world(vec2(0.0,0.0)),vec2(0.0,0.0));
+        assert_close(view.screen_to_world(vec2(800.0,0.0)),vec2(40.0,0.0));
+        assert_close(view.screen_to_world(vec2(800.0,600.0)),vec2(40.0,30.0));
+        assert_close(view.world_to_screen(vec2(10.0,20.0)),vec2(200.0,400.0));
+    }
+
+    #[test]
+    fn letterbox_offsets(){
+        //too tall, bars above and below
+        let view=rect_camera(FitMode::Letterbox([0.0;3])).view(vec2(800,800));
+        assert_close(view.offset,vec2(0.0,100.0));
+        assert_close(view.size,vec2(800.0,600.0));
+        assert_close(view.scale,vec2(20.0,20.0));
+        assert_close(view.screen_to_world(vec2(0.0,100.0)),vec2(0.0,0.0));
+        assert_close(view.screen_to_world(vec2(800.0,700.0)),vec2(40.0,30.0));
+
+        //too wide, bars left and right
+        let view=rect_camera(FitMode::Letterbox([0.0;3])).view(vec2(1000,600));
+        assert_close(view.offset,vec2(100.0,0.0));
+        assert_close(view.size,vec2(800.0,600.0));
+        assert_close(view.screen_to_world(vec2(100.0,0.0)),vec2(0.0,0.0));
+        assert_close(view.screen_to_world(vec2(900.0,600.0)),vec2(40.0,30.0));
+    }
+
+    #[test]
+    fn crop_and_expand_fill_the_window(){
+        let view=rect_camera(FitMode::Crop).view(vec2(800,800));
+        assert_close(view.offset,vec2(0.0,0.0));
+        assert_close(view.size,vec2(800.0,800.0));
+        //the sides are cut off
+        assert_close(view.screen_to_world(vec2(0.0,0.0)),vec2(5.0,0.0));
+        assert_close(view.screen_to_world(vec2(800.0,800.0)),vec2(35.0,30.0));
+
+        let view=rect_camera(FitMode::Expand).view(vec2(800,800));
+        assert_close(view.offset,vec2(0.0,0.0));
+        assert_close(view.scale,vec2(20.0,20.0));
+        //more world above and below
+        assert_close(view.screen_to_world(vec2(0.0,0.0)),vec2(0.0,-5.0));
+        assert_close(view.screen_to_world(vec2(800.0,800.0)),vec2(40.0,35.0));
+    }
+
+    #[test]
+    fn logical_pixels_scale_by_hidpi_factor(){
+        let view=rect_camera(FitMode::Stretch).view(vec2(800,600));
+        assert_close(view.logical_to_world(vec2(200.0,150.0),2.0),vec2(20.0,15.0));
+        assert_close(view.logical_to_world(vec2(400.0,300.0),2.0),vec2(40.0,30.0));
+        assert_close(view.world_to_logical(vec2(40.0,30.0),2.0),vec2(400.0,300.0));
+        assert_close(view.logical_to_world(vec2(320.0,240.0),2.5),vec2(40.0,30.0));
+    }
+
+    #[test]
+    fn center_is_in_the_middle(){
+        for camera in cameras(){
+            let view=camera.view(vec2(800,600));
+            assert_close(view.world_to_screen(camera.center),vec2(400.0,300.0));
+        }
+    }
+}
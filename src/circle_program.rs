@@ -1,15 +1,24 @@
-use axgeom;
-use axgeom::*;
 use crate::shader::*;
 use crate::gl;
+use crate::debug;
 use crate::gl::types::*;
 
 use crate::vbo::Buffer;
+use crate::program_cache::ProgramCache;
+use crate::camera::*;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::colormap::*;
+use crate::trails::Trails;
+use crate::texture::Texture2D;
+use crate::stats;
+use crate::state;
+use crate::profiler;
+use crate::postprocess::Uniform;
 use core::mem;
 
 use std::ptr;
 use std::str;
-use std::ffi::CString;
 
 // Shader sources
 static VS_SRC: &'static str = "
@@ -36,6 +45,11 @@ in float alpha2;
 uniform vec3 bcol;
 out vec4 out_color;
 uniform bool square;
+uniform bool use_colormap;
+uniform sampler2D colormap;
+uniform vec2 range;
+uniform bool sprite;
+uniform sampler2D sprite_tex;
 void main() {
 
     vec2 coord = gl_PointCoord - vec2(0.5);
@@ -46,7 +60,17 @@ void main() {
             discard;
     }
 
-    out_color = vec4(bcol,alpha2);
+    if (use_colormap){
+        float t = clamp((alpha2-range.x)/(range.y-range.x),0.0,1.0);
+        t = t*(255.0/256.0)+0.5/256.0;
+        out_color = vec4(texture(colormap,vec2(t,0.5)).rgb,1.0);
+    }else{
+        out_color = vec4(bcol,alpha2);
+    }
+
+    if (sprite){
+        out_color *= texture(sprite_tex,gl_PointCoord);
+    }
 }";
 
 
@@ -57,128 +81,69 @@ void main() {
 pub struct Vertex(pub [f32;3]);
 
 
+///How the points of a draw call are colored.
+#[derive(Copy,Clone,Debug)]
+pub enum ColorMode{
+    ///One color for every point. The third vertex component is alpha.
+    Solid([f32;3]),
+    ///The third vertex component is a scalar mapped through the colormap.
+    ///Values at or below min get the first color, at or above max the last.
+    Scalar{colormap:Colormap,min:f32,max:f32},
+}
 
 
 
-fn set_border_radius(program:GLuint,game_world:Rect<f32>,width:usize,height:usize,point_size:f32,square:bool){
-    let width=width as f32;
-    let _height=height as f32;
-
-    let ((x1,x2),(y1,y2))=game_world.get();
-    let w=x2-x1;
-    let h=y2-y1;
-
-    let scalex=2.0/w;
-    let scaley=2.0/h;
-
-
-    //let tx=x1-(w/2.0);
-    //let ty=y1-(h/2.0);
-    //dbg!(w,h);
-    //dbg!(x1,y1);
-    let tx=-(1.+x1/(w/2.0));
-    let ty=(1.+y1/(h/2.0));
-    //dbg!(tx,ty);
-    //let tx=-1.;
-    //let ty=1.;
-    //let tx=0.0;
-    //let ty=0.0;
-    
-    unsafe{
-        let matrix= [
-                [scalex, 0.0, 0.0],
-                [0.0, -scaley,0.0],
-                [tx,ty,1.0]
-            ];    
-
-        
-
-        gl::UseProgram(program);
-        
-
 
-        let point_size2=point_size*(width/w);
-        
-        //dbg!(width,w,point_size,point_size2);
 
-        
-        let myloc:GLint = gl::GetUniformLocation(program, CString::new("square").unwrap().as_ptr());
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-    
-        let square=if square{1}else{0};
-        gl::Uniform1i(myloc,square);
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-        
+fn set_border_radius(program:GLuint,view:&View,point_size:f32,square:bool){
+    let point_size2=point_size*view.scale.x;
 
-        let myloc:GLint = gl::GetUniformLocation(program, CString::new("point_size").unwrap().as_ptr());
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-    
-        gl::Uniform1f(myloc,point_size2);
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-    
-        let myloc:GLint = gl::GetUniformLocation(program, CString::new("mmatrix").unwrap().as_ptr());
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-    
-        gl::UniformMatrix3fv(myloc,1, 0,std::mem::transmute(&matrix[0][0]));
-        assert_eq!(gl::GetError(),gl::NO_ERROR);
-    
-    }
+    state::set_uniform(program,"square",Uniform::Int(if square{1}else{0}));
+    state::set_uniform(program,"point_size",Uniform::Float(point_size2));
+    state::set_uniform(program,"mmatrix",Uniform::Mat3(view.matrix));
+    debug::check_error();
 }
 
 
 
 
 pub struct CircleProgram{
-    program:GLuint,
-    fs:GLuint,
-    vs:GLuint,
-    
+    program:Program,
+    colormaps:ColormapTextures,
 }
 
 impl CircleProgram{
     pub fn new()->CircleProgram{
-
-        // Create GLSL shaders
-        let vs = compile_shader(VS_SRC, gl::VERTEX_SHADER);
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        
-        let fs = compile_shader(FS_SRC, gl::FRAGMENT_SHADER);
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        
-        let program = link_program(vs, fs);
-
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        
-        //Self::set_border_radius(program,game_world,width as usize,height as usize,point_size,true);
-        CircleProgram{program,fs,vs}
+        Self::with_cache(None)
     }
 
-    pub fn new_draw_session(&mut self,back_color:[f32;3],border:Rect<f32>)->DrawSession{
-        
-        
-        unsafe{
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            gl::Enable( gl::BLEND );
-
-            gl::ClearColor(back_color[0], back_color[1], back_color[2], 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
-        DrawSession{a:self,border}
+    ///Same as new() but tries to load the linked program from the cache first.
+    pub fn new_cached(cache:&ProgramCache)->CircleProgram{
+        Self::with_cache(Some(cache))
     }
 
-}
+    fn with_cache(cache:Option<&ProgramCache>)->CircleProgram{
+        CircleProgram{program:Program::new("CircleProgram",VS_SRC,FS_SRC,cache),colormaps:ColormapTextures::default()}
+    }
 
+    ///Clears the viewport and sets it up for drawing with the camera.
+    ///The point sizes are computed from the viewport's own pixel size.
+    pub fn new_draw_session(&mut self,back_color:[f32;3],camera:&Camera2D,viewport:&Viewport)->DrawSession{
+        self.program.restore();
+        let view=viewport.begin(camera,back_color);
+        DrawSession{a:self,view}
+    }
 
-impl Drop for CircleProgram{
-    fn drop(&mut self){
-        println!("dropping ");
-        // Cleanup
-        unsafe {
-            gl::DeleteProgram(self.program);
-            gl::DeleteShader(self.fs);
-            gl::DeleteShader(self.vs);
-        }
+    ///Draws into the trails target. Instead of clearing, the background
+    ///is blended over the previous frame to make it fade.
+    ///Call Trails::present() once the session is dropped.
+    pub fn new_trails_session(&mut self,trails:&mut Trails,camera:&Camera2D)->DrawSession{
+        self.program.restore();
+        let viewport=trails.begin_frame();
+        let view=viewport.begin_keep(camera);
+        DrawSession{a:self,view}
     }
+
 }
 
 
@@ -186,32 +151,73 @@ impl Drop for CircleProgram{
 
 pub struct DrawSession<'a>{
     a:&'a mut CircleProgram,
-    border:Rect<f32>,
+    view:View,
+}
+
+impl<'a> Drop for DrawSession<'a>{
+    fn drop(&mut self){
+        Viewport::end();
+    }
 }
 
 impl<'a> DrawSession<'a>{
+    ///The view this session draws with.
+    ///Use it to convert between window and world coordinates.
+    pub fn view(&self)->&View{
+        &self.view
+    }
+
+    pub fn draw_vbo_section(&mut self,buffer:&Buffer<Vertex>,start:usize,end:usize,color:ColorMode,radius:f32,square:bool,blend:BlendMode){
+        self.draw(buffer,start,end,color,radius,square,None,blend);
+    }
 
-    pub fn draw_vbo_section(&mut self,dim:Vec2<usize>,buffer:&Buffer<Vertex>,start:usize,end:usize,color:[f32;3],radius:f32,square:bool){
-        let (width,height) = (dim.x,dim.y);
+    ///Draws every point as the texture, tinted by the color.
+    ///Use ColorMode::Solid([1.0;3]) for the plain texture.
+    pub fn draw_vbo_sprites(&mut self,buffer:&Buffer<Vertex>,start:usize,end:usize,texture:&Texture2D,color:ColorMode,radius:f32,blend:BlendMode){
+        self.draw(buffer,start,end,color,radius,false,Some(texture),blend);
+    }
 
+    fn draw(&mut self,buffer:&Buffer<Vertex>,start:usize,end:usize,color:ColorMode,radius:f32,square:bool,sprite:Option<&Texture2D>,blend:BlendMode){
+        let _scope=profiler::scope("circles");
+        let program=self.a.program.get_id();
+        blend.apply();
         unsafe{
-            set_border_radius(self.a.program,self.border,width,height,radius,square);
-            
-            assert_eq!(gl::GetError(),gl::NO_ERROR);
+            set_border_radius(program,&self.view,radius,square);
+
+            state::set_uniform(program,"sprite",Uniform::Int(if sprite.is_some(){1}else{0}));
 
-            // Clear the screen to black
+            if let Some(texture)=sprite{
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_2D,texture.get_id());
+                gl::ActiveTexture(gl::TEXTURE0);
+
+                state::set_uniform(program,"sprite_tex",Uniform::Int(1));
+            }
             
-            //TODO move this down more?
-            gl::BindBuffer(gl::ARRAY_BUFFER, buffer.get_id());
+            debug::check_error();
+
+            state::bind_array_buffer(buffer.get_id());
             
-            let myloc:GLint = gl::GetUniformLocation(self.a.program, CString::new("bcol").unwrap().as_ptr());
-      
-            gl::Uniform3fv(myloc,1,std::mem::transmute(&color[0]));
+            let (bcol,colormap)=match color{
+                ColorMode::Solid(bcol)=>(bcol,None),
+                ColorMode::Scalar{colormap,min,max}=>([0.0;3],Some((colormap,min,max)))
+            };
+
+            state::set_uniform(program,"bcol",Uniform::Vec3(bcol));
+            state::set_uniform(program,"use_colormap",Uniform::Int(if colormap.is_some(){1}else{0}));
+
+            if let Some((colormap,min,max))=colormap{
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D,self.a.colormaps.get(colormap));
+
+                state::set_uniform(program,"colormap",Uniform::Int(0));
+                state::set_uniform(program,"range",Uniform::Vec2([min,max]));
+            }
                     
-            assert_eq!(gl::GetError(),gl::NO_ERROR);
+            debug::check_error();
 
             /////
-            let pos_attr = gl::GetAttribLocation(self.a.program, CString::new("position").unwrap().as_ptr());
+            let pos_attr = state::attrib_location(program,"position");
             gl::EnableVertexAttribArray(pos_attr as GLuint);
             gl::VertexAttribPointer(
                 pos_attr as GLuint,
@@ -224,7 +230,7 @@ impl<'a> DrawSession<'a>{
             /////
             
             
-            let pos_attr = gl::GetAttribLocation(self.a.program, CString::new("alpha").unwrap().as_ptr());
+            let pos_attr = state::attrib_location(program,"alpha");
             gl::EnableVertexAttribArray(pos_attr as GLuint);
             gl::VertexAttribPointer(
                 pos_attr as GLuint,
@@ -236,7 +242,8 @@ impl<'a> DrawSession<'a>{
             );
             
             //////
-            gl::DrawArrays(gl::POINTS,start as i32, end as i32);
+            gl::DrawArrays(gl::POINTS,start as i32,(end-start) as i32);
+            stats::draw_call(end-start);
         }
     }
 }
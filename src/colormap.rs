@@ -0,0 +1,92 @@
+use axgeom::*;
+use crate::texture::*;
+
+
+
+
+///Built in colormaps for mapping a scalar to a color.
+#[derive(Copy,Clone,Debug,PartialEq,Eq,Hash)]
+pub enum Colormap{
+    Viridis,
+    Magma,
+    Plasma,
+    ///Diverging, blue through gray to red.
+    Coolwarm,
+    Grayscale,
+}
+
+//Evenly spaced stops, linearly interpolated in between.
+static VIRIDIS:[u32;9]=[0x440154,0x472d7b,0x3b528b,0x2c728e,0x21918c,0x28ae80,0x5ec962,0xaddc30,0xfde725];
+static MAGMA:[u32;9]=[0x000004,0x1c1044,0x4f127b,0x812581,0xb5367a,0xe55064,0xfb8761,0xfec287,0xfcfdbf];
+static PLASMA:[u32;9]=[0x0d0887,0x4c02a1,0x7e03a8,0xa92395,0xcc4778,0xe56b5d,0xf89441,0xfdc328,0xf0f921];
+static COOLWARM:[u32;9]=[0x3b4cc0,0x6282ea,0x8db0fe,0xb8d0f9,0xdddddd,0xf5c4ad,0xf49a7b,0xe36a53,0xb40426];
+static GRAYSCALE:[u32;2]=[0x000000,0xffffff];
+
+///The number of texels in a colormap texture.
+pub(crate) const TEXTURE_SIZE:usize=256;
+
+impl Colormap{
+    fn stops(self)->&'static [u32]{
+        match self{
+            Colormap::Viridis=>&VIRIDIS,
+            Colormap::Magma=>&MAGMA,
+            Colormap::Plasma=>&PLASMA,
+            Colormap::Coolwarm=>&COOLWARM,
+            Colormap::Grayscale=>&GRAYSCALE,
+        }
+    }
+
+    ///The color at t, which is clamped to 0 to 1.
+    pub fn sample(self,t:f32)->[f32;3]{
+        let stops=self.stops();
+        let t=t.clamp(0.0,1.0)*(stops.len()-1) as f32;
+        let i=(t.floor() as usize).min(stops.len()-2);
+        let f=t-i as f32;
+
+        let unpack=|c:u32|[
+            ((c>>16)&0xff) as f32/255.0,
+            ((c>>8)&0xff) as f32/255.0,
+            (c&0xff) as f32/255.0
+        ];
+        let a=unpack(stops[i]);
+        let b=unpack(stops[i+1]);
+        [a[0]+(b[0]-a[0])*f,a[1]+(b[1]-a[1])*f,a[2]+(b[2]-a[2])*f]
+    }
+
+    ///A TEXTURE_SIZE by 1 lookup texture. To sample value t use
+    ///u=t*(TEXTURE_SIZE-1)/TEXTURE_SIZE+0.5/TEXTURE_SIZE so the ends hit texel centers.
+    pub(crate) fn create_texture(self)->Texture2D{
+        let mut data=Vec::with_capacity(TEXTURE_SIZE*4);
+        for i in 0..TEXTURE_SIZE{
+            let c=self.sample(i as f32/(TEXTURE_SIZE-1) as f32);
+            data.extend_from_slice(&[
+                (c[0]*255.0).round() as u8,
+                (c[1]*255.0).round() as u8,
+                (c[2]*255.0).round() as u8,
+                255
+            ]);
+        }
+
+        Texture2D::from_rgba(vec2(TEXTURE_SIZE,1),&data,TextureOptions::default())
+    }
+}
+
+
+//Lazily created lookup textures, one per colormap used.
+#[derive(Default)]
+pub(crate) struct ColormapTextures{
+    textures:Vec<(Colormap,Texture2D)>
+}
+
+impl ColormapTextures{
+    pub(crate) fn get(&mut self,colormap:Colormap)->u32{
+        self.textures.retain(|(_,t)|t.is_valid());
+        if let Some((_,t))=self.textures.iter().find(|(c,_)|*c==colormap){
+            return t.get_id();
+        }
+        let t=colormap.create_texture();
+        let id=t.get_id();
+        self.textures.push((colormap,t));
+        id
+    }
+}
@@ -0,0 +1,144 @@
+//Ties gl objects to the context that made them.
+//
+//Every object remembers the id of the context that was current when it was
+//created. Dropping it deletes it right away if that context is current on the
+//dropping thread. Otherwise the deletion waits until the owning GlSys next
+//swaps buffers, or is thrown away if that GlSys is gone, since its objects went with it.
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::state;
+
+use std::cell::Cell;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64,Ordering};
+
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub(crate) struct ContextId(u64);
+
+#[derive(Copy,Clone,Debug)]
+pub(crate) enum Object{
+    Buffer(GLuint),
+    Program(GLuint),
+    Shader(GLuint),
+    Texture(GLuint),
+    Framebuffer(GLuint),
+    Renderbuffer(GLuint),
+    Query(GLuint),
+}
+
+struct Queue{
+    live:Vec<ContextId>,
+    pending:Vec<(ContextId,Object)>,
+}
+
+static NEXT_ID:AtomicU64=AtomicU64::new(1);
+static QUEUE:Mutex<Queue>=Mutex::new(Queue{live:Vec::new(),pending:Vec::new()});
+
+thread_local!{
+    static CURRENT:Cell<Option<ContextId>>=const{Cell::new(None)};
+}
+
+
+//Called by GlSys once its context is current on this thread.
+pub(crate) fn create()->ContextId{
+    let id=ContextId(NEXT_ID.fetch_add(1,Ordering::Relaxed));
+    QUEUE.lock().unwrap().live.push(id);
+    CURRENT.with(|c|c.set(Some(id)));
+    state::reset();
+    id
+}
+
+//Called by GlSys when it is dropped, before the context is destroyed.
+pub(crate) fn destroy(id:ContextId){
+    {
+        let mut q=QUEUE.lock().unwrap();
+        q.live.retain(|&l|l!=id);
+        q.pending.retain(|&(c,_)|c!=id);
+    }
+    CURRENT.with(|c|if c.get()==Some(id){
+        c.set(None);
+    });
+    state::reset();
+}
+
+//Called when the context is known to be current on this thread,
+//since another GlSys may have made its own current in the meantime.
+pub(crate) fn make_current(id:ContextId){
+    CURRENT.with(|c|c.set(Some(id)));
+}
+
+//The context new objects belong to.
+pub(crate) fn current()->ContextId{
+    try_current().expect("no gl context is current on this thread, create a GlSys first")
+}
+
+pub(crate) fn try_current()->Option<ContextId>{
+    CURRENT.with(|c|c.get())
+}
+
+//Whether owner was destroyed, or replaced by GlSys::recover(), taking its objects with it.
+pub(crate) fn is_lost(owner:ContextId)->bool{
+    !QUEUE.lock().unwrap().live.contains(&owner)
+}
+
+//Whether an object made in owner has to be made again in the current context.
+//False if no context is current, since there is nothing to make it in.
+//Objects can't move between two live contexts, so using one while
+//another GlSys has its context current panics.
+pub(crate) fn is_stale(owner:ContextId)->bool{
+    match try_current(){
+        Some(c) if c!=owner=>{
+            assert!(is_lost(owner),"gl object used while the context of another GlSys is current");
+            true
+        },
+        _=>false
+    }
+}
+
+pub(crate) fn delete(owner:ContextId,objects:&[Object]){
+    if try_current()==Some(owner){
+        for &o in objects.iter(){
+            delete_now(o);
+        }
+        return;
+    }
+    let mut q=QUEUE.lock().unwrap();
+    if q.live.contains(&owner){
+        log::debug!(target:"gl::lifecycle",objects:?=objects;"deferring deletion until the context is current");
+        q.pending.extend(objects.iter().map(|&o|(owner,o)));
+    }
+}
+
+//Deletes what was dropped while the context wasn't current. Called by GlSys::swap_buffers().
+//The context has to be current, or the ids would name another context's objects.
+pub(crate) fn collect(id:ContextId){
+    assert_eq!(try_current(),Some(id),"collecting deletions for a context that isn't current");
+    let objects:Vec<Object>={
+        let mut q=QUEUE.lock().unwrap();
+        if q.pending.is_empty(){
+            return;
+        }
+        let (mine,rest)=q.pending.drain(..).partition::<Vec<_>,_>(|&(c,_)|c==id);
+        q.pending=rest;
+        mine.into_iter().map(|(_,o)|o).collect()
+    };
+    for o in objects{
+        delete_now(o);
+    }
+}
+
+fn delete_now(object:Object){
+    unsafe{
+        match object{
+            Object::Buffer(b)=>state::delete_buffer(b),
+            Object::Program(p)=>state::delete_program(p),
+            Object::Shader(s)=>gl::DeleteShader(s),
+            Object::Texture(t)=>gl::DeleteTextures(1,&t),
+            Object::Framebuffer(f)=>gl::DeleteFramebuffers(1,&f),
+            Object::Renderbuffer(r)=>gl::DeleteRenderbuffers(1,&r),
+            Object::Query(q)=>gl::DeleteQueriesEXT(1,&q),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//Routes KHR_debug messages from the driver to the log crate,
+//and names gl objects so those messages say which one they are about.
+
+use crate::gl;
+use crate::gl::types::*;
+
+use std::cell::Cell;
+use std::ffi::c_void;
+
+thread_local!{
+    static ENABLED:Cell<bool>=const{Cell::new(false)};
+}
+
+
+//Installs the message callback if KHR_debug is there. Returns whether it is.
+pub(crate) fn install()->bool{
+    let supported=crate::has_gl_extension("GL_KHR_debug")
+        && gl::DebugMessageCallbackKHR::is_loaded()
+        && gl::ObjectLabelKHR::is_loaded();
+    if supported{
+        unsafe{
+            gl::Enable(gl::DEBUG_OUTPUT_KHR);
+            //so the message arrives while the call that caused it is still on the stack
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS_KHR);
+            gl::DebugMessageCallbackKHR(callback,std::ptr::null());
+        }
+    }
+    ENABLED.with(|e|e.set(supported));
+    supported
+}
+
+//identifier is the kind of object, e.g. gl::BUFFER_KHR or gl::PROGRAM_KHR.
+pub(crate) fn label(identifier:GLenum,name:GLuint,label:&str){
+    if !ENABLED.with(|e|e.get()){
+        return;
+    }
+    unsafe{
+        gl::ObjectLabelKHR(identifier,name,label.len() as GLsizei,label.as_ptr() as *const GLchar);
+    }
+}
+
+//Logs a gl error raised since the last check, when KHR_debug isn't there.
+//With it the callback has already reported the error, with more detail.
+#[track_caller]
+pub(crate) fn check_error(){
+    if ENABLED.with(|e|e.get()){
+        return;
+    }
+    let error=unsafe{gl::GetError()};
+    if error!=gl::NO_ERROR{
+        let at=std::panic::Location::caller();
+        log::error!(target:"gl","gl error {:#x} at {}:{}",error,at.file(),at.line());
+    }
+}
+
+//Takes every error still queued, logging them like check_error() does,
+//so the next GetError() only returns what the calls after this raise.
+#[track_caller]
+pub(crate) fn flush_errors(){
+    let enabled=ENABLED.with(|e|e.get());
+    loop{
+        let error=unsafe{gl::GetError()};
+        if error==gl::NO_ERROR{
+            break;
+        }
+        if !enabled{
+            let at=std::panic::Location::caller();
+            log::error!(target:"gl","gl error {:#x} before {}:{}",error,at.file(),at.line());
+        }
+    }
+}
+
+extern "system" fn callback(source:GLenum,ty:GLenum,id:GLuint,severity:GLenum,length:GLsizei,message:*const GLchar,_user:*mut c_void){
+    //some drivers pass a negative length for null terminated messages
+    let message=unsafe{
+        if length<0{
+            std::ffi::CStr::from_ptr(message).to_bytes()
+        }else{
+            std::slice::from_raw_parts(message as *const u8,length as usize)
+        }
+    };
+    let message=String::from_utf8_lossy(message);
+    let level=match severity{
+        gl::DEBUG_SEVERITY_HIGH_KHR=>log::Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM_KHR=>log::Level::Warn,
+        gl::DEBUG_SEVERITY_LOW_KHR=>log::Level::Info,
+        _=>log::Level::Debug
+    };
+    log::log!(target:"gl",level,"{} {} {}: {}",source_name(source),type_name(ty),id,message.trim_end());
+}
+
+fn source_name(source:GLenum)->&'static str{
+    match source{
+        gl::DEBUG_SOURCE_API_KHR=>"api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM_KHR=>"window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER_KHR=>"shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY_KHR=>"third party",
+        gl::DEBUG_SOURCE_APPLICATION_KHR=>"application",
+        _=>"other"
+    }
+}
+
+fn type_name(ty:GLenum)->&'static str{
+    match ty{
+        gl::DEBUG_TYPE_ERROR_KHR=>"error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR_KHR=>"deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR_KHR=>"undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY_KHR=>"portability",
+        gl::DEBUG_TYPE_PERFORMANCE_KHR=>"performance",
+        gl::DEBUG_TYPE_MARKER_KHR=>"marker",
+        _=>"other"
+    }
+}
@@ -0,0 +1,288 @@
+use axgeom::*;
+use crate::atlas::*;
+use crate::texture::*;
+use crate::font8x13::FONT_8X13;
+
+
+
+
+//Distance field texels of one em, and how far the field reaches outside the glyph.
+const EM_TEXELS:usize=52;
+const SPREAD:usize=6;
+//The mask the field is computed from is this much finer than the field.
+const MASK_SCALE:usize=4;
+
+
+#[derive(Copy,Clone,Debug)]
+struct Glyph{
+    //None for glyphs with nothing to draw, like space
+    sprite:Option<u32>,
+    //Everything below is in ems, relative to the pen on the baseline.
+    offset:Vec2<f32>,
+    size:Vec2<f32>,
+    advance:f32,
+}
+
+
+///A signed distance field glyph atlas.
+///
+///Text drawn from it stays sharp at any size and can be outlined.
+///All metrics are in ems, where one em is the height of a line.
+pub struct SdfFont{
+    atlas:SpriteAtlas,
+    glyphs:Vec<(char,Glyph)>,
+    ascent:f32,
+    line_gap:f32,
+    spread:f32,
+}
+
+impl SdfFont{
+    ///The embedded X11 8x13 font with the printable ascii characters.
+    pub fn builtin()->SdfFont{
+        let texels_per_pixel=EM_TEXELS/13;
+        let dim=vec2(8*texels_per_pixel+2*SPREAD,13*texels_per_pixel+2*SPREAD);
+        let pad=SPREAD*MASK_SCALE;
+        let cell=texels_per_pixel*MASK_SCALE;
+
+        let mut builder=AtlasBuilder::new(1024);
+        let mut glyphs=Vec::new();
+        for (i,rows) in FONT_8X13.iter().enumerate(){
+            let c=(b' '+i as u8) as char;
+            let sprite=if c==' '{
+                None
+            }else{
+                let mask=create_mask(vec2(dim.x*MASK_SCALE,dim.y*MASK_SCALE),|x,y|{
+                    if x<pad || y<pad{
+                        return false;
+                    }
+                    let (px,py)=((x-pad)/cell,(y-pad)/cell);
+                    px<8 && py<13 && rows[py]&(0x80>>px)!=0
+                });
+                Some(builder.add(dim,&distance_field(&mask,dim)))
+            };
+
+            let em=1.0/13.0;
+            let spread=SPREAD as f32/EM_TEXELS as f32;
+            glyphs.push((c,Glyph{
+                sprite,
+                offset:vec2(-spread,-11.0*em-spread),
+                size:vec2(dim.x as f32/EM_TEXELS as f32,dim.y as f32/EM_TEXELS as f32),
+                advance:8.0*em
+            }));
+        }
+        Self::build(builder,glyphs,11.0/13.0,0.0)
+    }
+
+    ///Rasterizes the characters of a TrueType font into a distance field.
+    ///Characters the font doesn't have are drawn as '?'.
+    #[cfg(feature="ttf")]
+    pub fn from_ttf(data:&[u8],chars:impl Iterator<Item=char>)->Result<SdfFont,rusttype::Error>{
+        use rusttype::{point,Scale};
+        let font=rusttype::Font::from_bytes(data)?;
+
+        let scale=Scale::uniform((EM_TEXELS*MASK_SCALE) as f32);
+        let v=font.v_metrics(scale);
+        let to_em=1.0/(EM_TEXELS*MASK_SCALE) as f32;
+        let pad=(SPREAD*MASK_SCALE) as i32;
+
+        let mut builder=AtlasBuilder::new(1024);
+        let mut glyphs=Vec::new();
+        for c in chars.chain(std::iter::once('?')){
+            let glyph=font.glyph(c);
+            if glyph.id().0==0 || glyphs.iter().any(|(g,_)|*g==c){
+                continue;
+            }
+            let glyph=glyph.scaled(scale);
+            let advance=glyph.h_metrics().advance_width*to_em;
+            let glyph=glyph.positioned(point(0.0,0.0));
+
+            let g=match glyph.pixel_bounding_box(){
+                Some(bb)=>{
+                    //round up to whole texels so the mask divides evenly
+                    let round=|v:i32|(v as usize).div_ceil(MASK_SCALE)*MASK_SCALE;
+                    let mask_dim=vec2(round(bb.width()+2*pad),round(bb.height()+2*pad));
+
+                    let mut coverage=vec![0.0f32;mask_dim.x*mask_dim.y];
+                    glyph.draw(|x,y,v|{
+                        coverage[(y as usize+pad as usize)*mask_dim.x+x as usize+pad as usize]=v;
+                    });
+                    let mask=create_mask(mask_dim,|x,y|coverage[y*mask_dim.x+x]>=0.5);
+
+                    let dim=vec2(mask_dim.x/MASK_SCALE,mask_dim.y/MASK_SCALE);
+                    Glyph{
+                        sprite:Some(builder.add(dim,&distance_field(&mask,dim))),
+                        offset:vec2((bb.min.x-pad) as f32*to_em,(bb.min.y-pad) as f32*to_em),
+                        size:vec2(mask_dim.x as f32*to_em,mask_dim.y as f32*to_em),
+                        advance
+                    }
+                },
+                None=>{
+                    Glyph{sprite:None,offset:vec2(0.0,0.0),size:vec2(0.0,0.0),advance}
+                }
+            };
+            glyphs.push((c,g));
+        }
+        Ok(Self::build(builder,glyphs,v.ascent*to_em,v.line_gap*to_em))
+    }
+
+    fn build(builder:AtlasBuilder,glyphs:Vec<(char,Glyph)>,ascent:f32,line_gap:f32)->SdfFont{
+        let atlas=builder.build(TextureOptions::default());
+        SdfFont{atlas,glyphs,ascent,line_gap,spread:SPREAD as f32/EM_TEXELS as f32}
+    }
+
+    fn glyph(&self,c:char)->Option<&Glyph>{
+        self.glyphs.iter().find(|(g,_)|*g==c).or_else(||self.glyphs.iter().find(|(g,_)|*g=='?')).map(|(_,g)|g)
+    }
+
+    ///The distance from the top of a line to its baseline.
+    pub fn ascent(&self)->f32{
+        self.ascent
+    }
+
+    ///The distance between the baselines of two lines.
+    pub fn line_height(&self)->f32{
+        1.0+self.line_gap
+    }
+
+    ///How far the distance field reaches outside the glyphs.
+    ///Outlines can't be wider than this.
+    pub fn spread(&self)->f32{
+        self.spread
+    }
+
+    ///The width and height of the text when drawn at the given size.
+    pub fn measure(&self,text:&str,size:f32)->Vec2<f32>{
+        let mut width:f32=0.0;
+        let mut lines=0;
+        for line in text.split('\n'){
+            width=width.max(self.line_width(line));
+            lines+=1;
+        }
+        vec2(width*size,(1.0+(lines-1) as f32*self.line_height())*size)
+    }
+
+    pub(crate) fn line_width(&self,line:&str)->f32{
+        line.chars().filter_map(|c|self.glyph(c)).map(|g|g.advance).sum()
+    }
+
+    pub(crate) fn get_atlas(&self)->&SpriteAtlas{
+        &self.atlas
+    }
+
+    //Calls func with the top left and bottom right of every glyph quad
+    //and the uv rect of its sprite, for a line with its pen starting at pos.
+    pub(crate) fn layout_line(&self,line:&str,pos:Vec2<f32>,size:f32,mut func:impl FnMut([f32;2],[f32;2],[f32;4])){
+        let mut x=pos.x;
+        for c in line.chars(){
+            let g=match self.glyph(c){
+                Some(g)=>g,
+                None=>continue
+            };
+            if let Some(sprite)=g.sprite{
+                let x0=x+g.offset.x*size;
+                let y0=pos.y+g.offset.y*size;
+                func([x0,y0],[x0+g.size.x*size,y0+g.size.y*size],self.atlas.get_rect(sprite));
+            }
+            x+=g.advance*size;
+        }
+    }
+}
+
+
+
+
+fn create_mask(dim:Vec2<usize>,func:impl Fn(usize,usize)->bool)->Vec<bool>{
+    let mut mask=Vec::with_capacity(dim.x*dim.y);
+    for y in 0..dim.y{
+        for x in 0..dim.x{
+            mask.push(func(x,y));
+        }
+    }
+    mask
+}
+
+//Turns a mask MASK_SCALE times finer than dim into dim RGBA texels.
+//The alpha is 0.5 on the edge and falls to zero SPREAD texels outside of it.
+fn distance_field(mask:&[bool],dim:Vec2<usize>)->Vec<u8>{
+    let (w,h)=(dim.x*MASK_SCALE,dim.y*MASK_SCALE);
+    assert_eq!(mask.len(),w*h);
+
+    const FAR:f32=1e20;
+    let mut to_outside:Vec<f32>=mask.iter().map(|&m|if m{FAR}else{0.0}).collect();
+    let mut to_inside:Vec<f32>=mask.iter().map(|&m|if m{0.0}else{FAR}).collect();
+    squared_distance_transform(&mut to_outside,w,h);
+    squared_distance_transform(&mut to_inside,w,h);
+
+    let mut data=Vec::with_capacity(dim.x*dim.y*4);
+    for y in 0..dim.y{
+        for x in 0..dim.x{
+            let i=(y*MASK_SCALE+MASK_SCALE/2)*w+x*MASK_SCALE+MASK_SCALE/2;
+            let d=if mask[i]{
+                to_outside[i].sqrt()-0.5
+            }else{
+                0.5-to_inside[i].sqrt()
+            };
+            let d=d/MASK_SCALE as f32;
+            let a=(0.5+d/(2.0*SPREAD as f32)).clamp(0.0,1.0);
+            data.extend_from_slice(&[255,255,255,(a*255.0).round() as u8]);
+        }
+    }
+    data
+}
+
+//Felzenszwalb and Huttenlocher's exact euclidean distance transform.
+//Cells that are zero are features, the rest end up with the squared
+//distance to the nearest feature.
+fn squared_distance_transform(grid:&mut [f32],w:usize,h:usize){
+    let n=w.max(h);
+    let mut f=vec![0.0;n];
+    let mut d=vec![0.0;n];
+    let mut v=vec![0;n];
+    let mut z=vec![0.0;n+1];
+
+    for x in 0..w{
+        for y in 0..h{
+            f[y]=grid[y*w+x];
+        }
+        transform_1d(&f[..h],&mut d[..h],&mut v,&mut z);
+        for y in 0..h{
+            grid[y*w+x]=d[y];
+        }
+    }
+    for y in 0..h{
+        f[..w].copy_from_slice(&grid[y*w..(y+1)*w]);
+        transform_1d(&f[..w],&mut d[..w],&mut v,&mut z);
+        grid[y*w..(y+1)*w].copy_from_slice(&d[..w]);
+    }
+}
+
+fn transform_1d(f:&[f32],d:&mut [f32],v:&mut [usize],z:&mut [f32]){
+    let intersect=|q:usize,p:usize|{
+        ((f[q]+(q*q) as f32)-(f[p]+(p*p) as f32))/(2.0*q as f32-2.0*p as f32)
+    };
+
+    let mut k=0;
+    v[0]=0;
+    z[0]=f32::NEG_INFINITY;
+    z[1]=f32::INFINITY;
+    for q in 1..f.len(){
+        let mut s=intersect(q,v[k]);
+        while s<=z[k]{
+            k-=1;
+            s=intersect(q,v[k]);
+        }
+        k+=1;
+        v[k]=q;
+        z[k]=s;
+        z[k+1]=f32::INFINITY;
+    }
+
+    k=0;
+    for q in 0..f.len(){
+        while z[k+1]<q as f32{
+            k+=1;
+        }
+        let dq=q as f32-v[k] as f32;
+        d[q]=dq*dq+f[v[k]];
+    }
+}
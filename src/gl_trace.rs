@@ -0,0 +1,88 @@
+//!Logs every gl call the crate makes, only built with the gl_trace feature.
+//!
+//!Each call is logged at trace level to the "gl" target with its arguments,
+//!return value and any error it raised. Errors are also logged at error level,
+//!so they show up without turning on trace logging.
+
+use crate::gl;
+use crate::gl::types::*;
+
+use std::cell::{Cell,RefCell};
+use std::fmt;
+use std::fs;
+use std::path::{Path,PathBuf};
+
+
+enum Capture{
+    Idle,
+    //waiting for the current frame to end
+    Armed(PathBuf),
+    Capturing(PathBuf,Vec<String>),
+}
+
+thread_local!{
+    static CAPTURE:RefCell<Capture>=const{RefCell::new(Capture::Idle)};
+    //An error a traced call took from gl that nobody has asked for yet.
+    static PENDING_ERROR:Cell<Option<GLenum>>=const{Cell::new(None)};
+}
+
+
+///Writes every gl call of the next whole frame to a text file, one per line.
+///The frame starts at the next GlSys::swap_buffers() and ends at the one after.
+///
+///The file has no addresses or timings other than what the calls were passed,
+///so captures of two runs can be diffed.
+pub fn capture_next_frame(path:impl AsRef<Path>){
+    CAPTURE.with(|c|*c.borrow_mut()=Capture::Armed(path.as_ref().to_path_buf()));
+}
+
+pub(crate) fn end_frame(){
+    CAPTURE.with(|c|{
+        let mut c=c.borrow_mut();
+        match std::mem::replace(&mut *c,Capture::Idle){
+            Capture::Idle=>{},
+            Capture::Armed(path)=>*c=Capture::Capturing(path,Vec::new()),
+            Capture::Capturing(path,lines)=>{
+                let mut text=lines.join("\n");
+                text.push('\n');
+                match fs::write(&path,text){
+                    Ok(())=>log::info!(target:"gl","captured {} calls to {}",lines.len(),path.display()),
+                    Err(e)=>log::error!(target:"gl","failed to write capture to {}: {}",path.display(),e)
+                }
+            }
+        }
+    });
+}
+
+//Called by the generated wrappers after each call. See build.rs.
+pub(crate) fn record(name:&str,args:fmt::Arguments,result:&dyn fmt::Debug){
+    let error=unsafe{gl::raw::GetError()};
+    if error!=gl::NO_ERROR{
+        log::error!(target:"gl","{}({}) raised {:#x}",name,args,error);
+        //gl only keeps the first error until it is read
+        PENDING_ERROR.with(|p|if p.get().is_none(){
+            p.set(Some(error));
+        });
+    }
+
+    let capturing=CAPTURE.with(|c|matches!(*c.borrow(),Capture::Capturing(..)));
+    if !capturing && !log::log_enabled!(target:"gl",log::Level::Trace){
+        return;
+    }
+
+    let mut line=format!("{}({}) -> {:?}",name,args,result);
+    if error!=gl::NO_ERROR{
+        line.push_str(&format!(" error {:#x}",error));
+    }
+    log::trace!(target:"gl","{}",line);
+    if capturing{
+        CAPTURE.with(|c|if let Capture::Capturing(_,lines)=&mut *c.borrow_mut(){
+            lines.push(line);
+        });
+    }
+}
+
+//Used by the wrapped glGetError().
+pub(crate) fn take_error()->Option<GLenum>{
+    PENDING_ERROR.with(|p|p.take())
+}
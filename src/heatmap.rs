@@ -0,0 +1,211 @@
+use axgeom::*;
+use crate::shader::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use crate::vbo::Buffer;
+use crate::circle_program::Vertex;
+use crate::camera::*;
+use crate::offscreen::OffscreenTarget;
+use crate::postprocess::{FullscreenPass,Uniform};
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::colormap::*;
+use crate::stats;
+use crate::state;
+use crate::profiler;
+use core::mem;
+
+use std::ptr;
+
+//The third vertex component scales the contribution of each point.
+static SPLAT_VS_SRC: &'static str = "
+#version 300 es
+in vec2 position;
+in float weight;
+uniform mat3 mmatrix;
+uniform float point_size;
+out float weight2;
+void main() {
+    gl_PointSize = point_size;
+    gl_Position = vec4(mmatrix * vec3(position, 1.0), 1.0);
+    weight2 = weight;
+}";
+
+//A gaussian that has fallen to about 1% at the edge of the point.
+static SPLAT_FS_SRC: &'static str = "
+#version 300 es
+precision highp float;
+in float weight2;
+uniform float scale;
+out vec4 out_color;
+void main() {
+    vec2 coord = (gl_PointCoord - vec2(0.5)) * 2.0;
+    float r2 = dot(coord, coord);
+    if (r2 > 1.0)
+        discard;
+    out_color = vec4(exp(-4.5 * r2) * weight2 * scale, 0.0, 0.0, 0.0);
+}";
+
+static COLORIZE_FS_SRC: &'static str = "
+#version 300 es
+precision highp float;
+in vec2 uv;
+uniform sampler2D tex0;
+uniform sampler2D tex1;
+uniform bool use_colormap;
+uniform float max_density;
+out vec4 out_color;
+
+vec3 heat(float t) {
+    return clamp(vec3(t * 3.0, t * 3.0 - 1.0, t * 3.0 - 2.0), 0.0, 1.0);
+}
+
+void main() {
+    float t = clamp(texture(tex0, uv).r / max_density, 0.0, 1.0);
+    vec3 c = use_colormap ? texture(tex1, vec2(t * (255.0 / 256.0) + 0.5 / 256.0, 0.5)).rgb : heat(t);
+    out_color = vec4(c, smoothstep(0.0, 0.05, t));
+}";
+
+
+
+
+///Renders a density map of a point cloud.
+///
+///Every point adds a gaussian kernel into a float offscreen target,
+///and the accumulated density is then mapped through a colormap.
+pub struct HeatmapProgram{
+    program:Program,
+    colorize:FullscreenPass,
+    density:OffscreenTarget,
+    colormap:Option<Colormap>,
+    colormaps:ColormapTextures,
+}
+
+impl HeatmapProgram{
+    pub fn new()->HeatmapProgram{
+        HeatmapProgram{
+            program:Program::new("HeatmapProgram",SPLAT_VS_SRC,SPLAT_FS_SRC,None),
+            colorize:FullscreenPass::new(COLORIZE_FS_SRC),
+            density:OffscreenTarget::new_float(vec2(1,1)),
+            colormap:None,
+            colormaps:ColormapTextures::default()
+        }
+    }
+
+    ///None uses a built in black, red, yellow, white ramp.
+    pub fn set_colormap(&mut self,colormap:Option<Colormap>){
+        self.colormap=colormap;
+    }
+
+    ///Starts accumulating density for the area the camera shows in the viewport.
+    ///Nothing is visible until HeatmapSession::finish() is called.
+    pub fn new_session(&mut self,back_color:[f32;3],camera:&Camera2D,viewport:&Viewport)->HeatmapSession{
+        self.program.restore();
+        let view=viewport.view(camera);
+        let dim=vec2((view.size.x.round() as usize).max(1),(view.size.y.round() as usize).max(1));
+        self.density.resize(dim);
+
+        unsafe{
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER,self.density.draw_fbo());
+            gl::Viewport(0,0,dim.x as GLsizei,dim.y as GLsizei);
+            gl::ClearColor(0.0,0.0,0.0,0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        state::set_blend(Some((gl::ONE,gl::ONE)));
+        HeatmapSession{a:self,view,camera:*camera,viewport:*viewport,back_color,finished:false}
+    }
+}
+
+
+
+
+pub struct HeatmapSession<'a>{
+    a:&'a mut HeatmapProgram,
+    view:View,
+    camera:Camera2D,
+    viewport:Viewport,
+    back_color:[f32;3],
+    finished:bool,
+}
+
+impl<'a> HeatmapSession<'a>{
+
+    ///Adds every point in the section.
+    ///radius is the world size of the kernel and weight the density at its peak.
+    ///Without half float support densities above one saturate,
+    ///so keep weight small in that case.
+    pub fn draw_vbo_section(&mut self,buffer:&Buffer<Vertex>,start:usize,end:usize,radius:f32,weight:f32){
+        let _scope=profiler::scope("heatmap");
+        let program=self.a.program.get_id();
+        unsafe{
+            state::use_program(program);
+
+            state::set_uniform(program,"point_size",Uniform::Float(radius*self.view.scale.x));
+
+            state::set_uniform(program,"scale",Uniform::Float(weight));
+
+            state::set_uniform(program,"mmatrix",Uniform::Mat3(self.view.matrix));
+            debug::check_error();
+
+            state::bind_array_buffer(buffer.get_id());
+
+            let pos_attr = state::attrib_location(program,"position");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                2,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                3*mem::size_of::<f32>() as i32,
+                ptr::null(),
+            );
+
+            let pos_attr = state::attrib_location(program,"weight");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                1,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                3*mem::size_of::<f32>() as i32,
+                (2*mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+
+            gl::DrawArrays(gl::POINTS,start as i32,(end-start) as i32);
+            stats::draw_call(end-start);
+            debug::check_error();
+        }
+    }
+
+    ///Clears the viewport and draws the density mapped through the colormap.
+    ///Densities at or above max_density get the hottest color.
+    pub fn finish(mut self,max_density:f32){
+        self.finished=true;
+        self.viewport.begin(&self.camera,self.back_color);
+        BlendMode::Alpha.apply();
+        let a=&mut *self.a;
+        let lut=match a.colormap{
+            Some(c)=>a.colormaps.get(c),
+            None=>0
+        };
+        a.colorize.draw_current(&[a.density.get_texture_id(),lut],&[
+            ("max_density",Uniform::Float(max_density)),
+            ("use_colormap",Uniform::Int(if a.colormap.is_some(){1}else{0}))
+        ]);
+        Viewport::end();
+    }
+}
+
+//Dropped without finish(), the density framebuffer and additive
+//blending would otherwise leak into whatever is drawn next.
+impl<'a> Drop for HeatmapSession<'a>{
+    fn drop(&mut self){
+        if !self.finished{
+            BlendMode::Alpha.apply();
+            Viewport::end();
+        }
+    }
+}
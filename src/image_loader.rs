@@ -0,0 +1,86 @@
+use axgeom::*;
+use crate::texture::*;
+use std::path::Path;
+
+pub use image::ImageError;
+
+
+
+
+///How decoded images are turned into textures.
+#[derive(Copy,Clone,Debug,Default)]
+pub struct ImageOptions{
+    ///Filtering, wrapping and mipmaps of the texture.
+    ///texture.srgb defaults to false, see TextureOptions::srgb.
+    pub texture:TextureOptions,
+    ///Multiply the color by alpha, for use with BlendMode::PremultipliedAlpha.
+    ///For sRGB textures this is done in linear space.
+    pub premultiply:bool,
+}
+
+
+///Decodes a PNG, JPEG or BMP file, picking the format from the extension.
+pub fn load_texture<P:AsRef<Path>>(path:P,options:ImageOptions)->Result<Texture2D,ImageError>{
+    let img=image::open(path)?.to_rgba();
+    Ok(create_texture(img,options))
+}
+
+///Decodes a PNG, JPEG or BMP image held in memory, picking the format from its header.
+pub fn load_texture_from_memory(bytes:&[u8],options:ImageOptions)->Result<Texture2D,ImageError>{
+    let img=image::load_from_memory(bytes)?.to_rgba();
+    Ok(create_texture(img,options))
+}
+
+///Decodes an image into RGBA texels, top row first, without creating a texture.
+///Only premultiply and texture.srgb of the options are used.
+///Useful for building a sprite atlas.
+pub fn load_rgba<P:AsRef<Path>>(path:P,options:ImageOptions)->Result<(Vec2<usize>,Vec<u8>),ImageError>{
+    let img=image::open(path)?.to_rgba();
+    Ok(decode(img,options))
+}
+
+
+fn decode(img:image::RgbaImage,options:ImageOptions)->(Vec2<usize>,Vec<u8>){
+    let dim=vec2(img.width() as usize,img.height() as usize);
+    let mut data=img.into_raw();
+    if options.premultiply{
+        premultiply(&mut data,options.texture.srgb);
+    }
+    (dim,data)
+}
+
+fn create_texture(img:image::RgbaImage,options:ImageOptions)->Texture2D{
+    let (dim,data)=decode(img,options);
+    Texture2D::from_rgba(dim,&data,options.texture)
+}
+
+fn premultiply(data:&mut [u8],srgb:bool){
+    for p in data.chunks_mut(4){
+        let a=p[3] as f32/255.0;
+        for c in p[..3].iter_mut(){
+            let v=*c as f32/255.0;
+            let v=if srgb{
+                to_srgb(to_linear(v)*a)
+            }else{
+                v*a
+            };
+            *c=(v*255.0).round() as u8;
+        }
+    }
+}
+
+fn to_linear(v:f32)->f32{
+    if v<=0.04045{
+        v/12.92
+    }else{
+        ((v+0.055)/1.055).powf(2.4)
+    }
+}
+
+fn to_srgb(v:f32)->f32{
+    if v<=0.0031308{
+        v*12.92
+    }else{
+        1.055*v.powf(1.0/2.4)-0.055
+    }
+}
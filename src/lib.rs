@@ -5,6 +5,7 @@ mod gl {
 
 
 mod shader;
+mod font8x13;
 
 
 //extern crate glutin;
@@ -26,15 +27,53 @@ use axgeom::*;
 
 pub mod circle_program;
 pub mod vbo;
+pub mod program_cache;
+pub mod camera;
+pub mod viewport;
+pub mod offscreen;
+pub mod blit_program;
+pub mod postprocess;
+pub mod blend;
+pub mod heatmap;
+pub mod colormap;
+pub mod trails;
+pub mod texture;
+pub mod atlas;
+pub mod sprite_program;
+#[cfg(feature="image")]
+pub mod image_loader;
+pub mod font;
+pub mod text_program;
+pub mod stats;
+pub mod profiler;
+pub mod app;
+mod state;
+mod overlay;
+mod debug;
+mod context;
+#[cfg(feature="gl_trace")]
+pub mod gl_trace;
 
 
 
 use glutin::PossiblyCurrent;
+use gl::types::*;
 
 
-
+///Owns the window and gl context.
+///
+///Debug builds ask for a debug context. If the driver has KHR_debug its messages
+///go to the log crate under the "gl" target, with buffers and programs named in them.
 pub struct GlSys{
     windowed_context:glutin::WindowedContext<PossiblyCurrent>,
+    overlay:Option<overlay::Overlay>,
+    profiler:profiler::GpuProfiler,
+    last_stats:stats::FrameStats,
+    ctx:context::ContextId,
+    samples:u16,
+    //whether the driver reports resets
+    robust:bool,
+    lost:bool,
 }
 
 
@@ -48,67 +87,244 @@ impl GlSys{
     ///0,height is bottom left
     ///width,height is bottom right
     pub fn new(events_loop:&glutin::event_loop::EventLoop<()>)->GlSys{
+        Self::with_samples(events_loop,0)
+    }
+
+    ///Same as new() but requests a multisampled default framebuffer.
+    ///samples should be a power of two, zero disables multisampling.
+    pub fn with_samples(events_loop:&glutin::event_loop::EventLoop<()>,samples:u16)->GlSys{
 
         let _border=axgeom::Rect::new(0.0,0.0,0.0,0.0);
         let _point_size=0.0;
 
-        use glutin::window::Fullscreen;
-        let fullscreen = Fullscreen::Borderless(prompt_for_monitor(events_loop));
+        let windowed_context=create_context(events_loop,prompt_for_monitor(events_loop),samples);
 
-        let gl_window = glutin::window::WindowBuilder::new()
-            .with_fullscreen(Some(fullscreen));
-         
-        //we are targeting only opengl 3.0 es. and glsl 300 es.
-        
-        let windowed_context = glutin::ContextBuilder::new()
-        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 0)))
-        .with_vsync(true)
-        .build_windowed(gl_window,&events_loop).unwrap();
+        let ctx=context::create();
+        let debug=debug::install();
+        let robust=has_robustness();
+        log::info!(target:"gl::lifecycle",version=gl_string(gl::VERSION).as_str(),renderer=gl_string(gl::RENDERER).as_str(),samples=samples,khr_debug=debug,robustness=robust;"created gl context");
+        debug::check_error();
+        let profiler=profiler::GpuProfiler::new();
+        profiler.make_current();
+        GlSys{windowed_context,overlay:None,profiler,last_stats:stats::FrameStats::default(),ctx,samples,robust,lost:false}
 
+    }
+    
+    
+    ///The size of the window in logical pixels.
+    pub fn get_dim(&self)->Vec2<usize>{
+        let glutin::dpi::LogicalSize{width,height}=self.windowed_context.window().inner_size();
+        vec2(width as usize,height as usize)
+    }
 
-        std::thread::sleep(std::time::Duration::from_millis(500));
-        
-        let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+    ///The size of the window in physical pixels.
+    ///This is what gl draws into, so this is what draw sessions want.
+    pub fn get_physical_dim(&self)->Vec2<usize>{
+        let window=self.windowed_context.window();
+        let glutin::dpi::PhysicalSize{width,height}=window.inner_size().to_physical(window.hidpi_factor());
+        vec2(width.round() as usize,height.round() as usize)
+    }
 
-        let glutin::dpi::LogicalSize{width: _,height: _}=windowed_context.window().inner_size();
+    ///Call when the window is resized. Some platforms need it
+    ///for the default framebuffer to match the window, app::run() calls it.
+    pub fn resize(&mut self,size:glutin::dpi::LogicalSize){
+        let hidpi_factor=self.windowed_context.window().hidpi_factor();
+        self.windowed_context.resize(size.to_physical(hidpi_factor));
+    }
 
-                
-        let windowed_context = unsafe { windowed_context.make_current() }.unwrap();
+    ///The ratio of physical to logical pixels.
+    pub fn hidpi_factor(&self)->f32{
+        self.windowed_context.window().hidpi_factor() as f32
+    }
 
-        let glutin::dpi::LogicalSize{width: _,height: _}=windowed_context.window().inner_size();
+    ///Shows fps, a frame time graph, gpu time and the counts of
+    ///stats::FrameStats in the top left corner of the window.
+    ///It is drawn by swap_buffers() over everything else.
+    pub fn set_overlay(&mut self,enabled:bool){
+        if enabled!=self.overlay.is_some(){
+            self.overlay=if enabled{Some(overlay::Overlay::new())}else{None};
+        }
+    }
 
-        // It is essential to make the context current before calling `gl::load_with`.
+    pub fn overlay_enabled(&self)->bool{
+        self.overlay.is_some()
+    }
 
-        //let cs=ContextSetup::new(windowed_context.context(),width as u32,height as u32,border,point_size);
+    ///A profiler that swap_buffers() ends the frame of.
+    ///The overlay shows the time of each of its passes.
+    pub fn gpu_profiler(&mut self)->&mut profiler::GpuProfiler{
+        &mut self.profiler
+    }
 
-        
+    ///The counts of the frame most recently presented by swap_buffers().
+    pub fn get_frame_stats(&self)->stats::FrameStats{
+        self.last_stats
+    }
 
-        // Load the OpenGL function pointers
-        gl::load_with(|symbol| windowed_context.get_proc_address(symbol) as *const _);
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        
-        
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        GlSys{windowed_context}
+    ///Whether the context was lost, after which nothing drawn shows up
+    ///until recover() is called. This happens when the gpu is reset,
+    ///on drivers that report it through EXT_robustness or when swapping buffers.
+    pub fn is_context_lost(&mut self)->bool{
+        if !self.lost && self.robust{
+            let status=unsafe{gl::GetGraphicsResetStatusEXT()};
+            if status!=gl::NO_ERROR{
+                log::error!(target:"gl::lifecycle",status=status;"gl context lost");
+                self.lost=true;
+            }
+        }
+        self.lost
+    }
 
+    ///Replaces a lost context with a new one, in a new window on the same monitor.
+    ///
+    ///Buffers and programs rebuild themselves the next time they are used,
+    ///and offscreen targets and trails when they are resized. Textures, sprite
+    ///atlases and fonts have no copy of their texels and have to be created
+    ///again by the application, see Texture2D::is_valid().
+    pub fn recover(&mut self,events_loop:&EventLoopWindowTarget<()>){
+        let overlay=self.overlay.is_some();
+        self.overlay=None;
+        context::destroy(self.ctx);
+
+        let monitor=self.windowed_context.window().current_monitor();
+        self.windowed_context=create_context(events_loop,monitor,self.samples);
+        self.ctx=context::create();
+        debug::install();
+        self.robust=has_robustness();
+        self.profiler=profiler::GpuProfiler::new();
+        self.profiler.make_current();
+        self.set_overlay(overlay);
+        self.lost=false;
+        log::info!(target:"gl::lifecycle","recovered from a lost gl context");
     }
-    
-    
-    pub fn get_dim(&self)->Vec2<usize>{
-        let glutin::dpi::LogicalSize{width,height}=self.windowed_context.window().inner_size();
-        vec2(width as usize,height as usize)
+
+    //Deletes objects dropped while the context wasn't current.
+    //Left for a later frame if another GlSys has its context current.
+    fn collect(&mut self){
+        if self.windowed_context.is_current(){
+            context::make_current(self.ctx);
+            context::collect(self.ctx);
+        }
     }
 
+    ///Does nothing once the context is lost, see is_context_lost().
     pub fn swap_buffers(&mut self){
-        self.windowed_context.swap_buffers().unwrap();
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
+        if self.is_context_lost(){
+            return;
+        }
+        self.last_stats=stats::take();
+        self.profiler.end_frame();
+        let dim=self.get_physical_dim();
+        let hidpi_factor=self.hidpi_factor();
+        if let Some(overlay)=self.overlay.as_mut(){
+            overlay.draw(dim,hidpi_factor,&self.last_stats,&self.profiler);
+            //the overlay's own drawing isn't part of the frame
+            stats::take();
+        }
+        match self.windowed_context.swap_buffers(){
+            Ok(())=>{},
+            Err(glutin::ContextError::ContextLost)=>{
+                log::error!(target:"gl::lifecycle","gl context lost while swapping buffers");
+                self.lost=true;
+                return;
+            },
+            Err(e)=>panic!("{:?}",e)
+        }
+        self.collect();
+        debug::check_error();
+        #[cfg(feature="gl_trace")]
+        gl_trace::end_frame();
     }
 
 }
 
 
+//Resources still alive after this are left to die with the context.
+impl Drop for GlSys{
+    fn drop(&mut self){
+        self.overlay=None;
+        self.collect();
+        context::destroy(self.ctx);
+        log::info!(target:"gl::lifecycle","destroyed gl context");
+    }
+}
+
+
+//Opens a fullscreen window on the monitor and makes its context current.
+fn create_context(events_loop:&EventLoopWindowTarget<()>,monitor:MonitorHandle,samples:u16)->glutin::WindowedContext<PossiblyCurrent>{
+    use glutin::window::Fullscreen;
+    let fullscreen = Fullscreen::Borderless(monitor);
+
+    let gl_window = glutin::window::WindowBuilder::new()
+        .with_fullscreen(Some(fullscreen));
+     
+    //we are targeting only opengl 3.0 es. and glsl 300 es.
+    
+    let mut builder = glutin::ContextBuilder::new()
+    .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 0)))
+    .with_vsync(true)
+    //a debug context makes drivers report far more through KHR_debug
+    .with_gl_debug_flag(cfg!(debug_assertions))
+    //so a gpu reset loses the context instead of leaving it undefined, see GlSys::is_context_lost()
+    .with_gl_robustness(glutin::Robustness::TryRobustLoseContextOnReset);
+    if samples>0{
+        builder=builder.with_multisampling(samples);
+    }
+    let windowed_context = builder.build_windowed(gl_window,events_loop).unwrap();
+
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    
+    let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+
+    let glutin::dpi::LogicalSize{width: _,height: _}=windowed_context.window().inner_size();
+
+            
+    let windowed_context = unsafe { windowed_context.make_current() }.unwrap();
+
+    let glutin::dpi::LogicalSize{width: _,height: _}=windowed_context.window().inner_size();
+
+    // It is essential to make the context current before calling `gl::load_with`.
+
+    //let cs=ContextSetup::new(windowed_context.context(),width as u32,height as u32,border,point_size);
+
+    
+
+    // Load the OpenGL function pointers
+    gl::load_with(|symbol| windowed_context.get_proc_address(symbol) as *const _);
+    debug::check_error();
+    windowed_context
+}
+
+fn has_robustness()->bool{
+    has_gl_extension("GL_EXT_robustness") && gl::GetGraphicsResetStatusEXT::is_loaded()
+}
+
+fn gl_string(name:GLenum)->String{
+    unsafe{
+        let s=gl::GetString(name);
+        if s.is_null(){
+            return String::new();
+        }
+        std::ffi::CStr::from_ptr(s as *const _).to_string_lossy().into_owned()
+    }
+}
+
+///Whether the current context supports the extension.
+pub(crate) fn has_gl_extension(name:&str)->bool{
+    unsafe{
+        let mut num:GLint=0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS,&mut num);
+        (0..num as GLuint).any(|i|{
+            let s=gl::GetStringi(gl::EXTENSIONS,i);
+            !s.is_null() && std::ffi::CStr::from_ptr(s as *const _).to_bytes()==name.as_bytes()
+        })
+    }
+}
+
+
 
-use glutin::event_loop::{EventLoop};
+use glutin::event_loop::{EventLoop,EventLoopWindowTarget};
 use glutin::monitor::{MonitorHandle};
 
 //use std::io::{stdin, stdout, Write};
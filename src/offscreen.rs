@@ -0,0 +1,233 @@
+use axgeom::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+use crate::viewport::Viewport;
+use crate::context::{self,ContextId,Object};
+
+use std::ptr;
+
+
+
+
+///A framebuffer object with a color texture attachment and
+///optionally a depth/stencil attachment.
+///
+///Draw sessions render into it through viewport(),
+///and BlitProgram draws its texture into another viewport.
+///
+///A multisampled target draws into multisampled renderbuffers
+///which resolve() blits into the texture.
+pub struct OffscreenTarget{
+    //the framebuffer with the texture attached
+    fbo:GLuint,
+    texture:GLuint,
+    //zero if there is none
+    depth_stencil:GLuint,
+    msaa:Option<Multisample>,
+    float:bool,
+    dim:Vec2<usize>,
+    ctx:ContextId,
+}
+
+struct Multisample{
+    fbo:GLuint,
+    color:GLuint,
+    samples:usize,
+}
+
+impl OffscreenTarget{
+    pub fn new(dim:Vec2<usize>)->OffscreenTarget{
+        Self::create(dim,false,0,false)
+    }
+
+    pub fn with_depth_stencil(dim:Vec2<usize>)->OffscreenTarget{
+        Self::create(dim,true,0,false)
+    }
+
+    ///The sample count is clamped to what the driver supports.
+    ///Zero or one samples gives a normal target.
+    pub fn new_multisampled(dim:Vec2<usize>,samples:usize,depth_stencil:bool)->OffscreenTarget{
+        Self::create(dim,depth_stencil,samples,false)
+    }
+
+    ///A half float target, for accumulating values outside of 0 to 1.
+    ///Falls back to eight bits per channel if the driver can't render to half floats,
+    ///see is_float().
+    pub fn new_float(dim:Vec2<usize>)->OffscreenTarget{
+        Self::create(dim,false,0,true)
+    }
+
+    fn create(dim:Vec2<usize>,depth_stencil:bool,samples:usize,float:bool)->OffscreenTarget{
+        let float=float && (
+            crate::has_gl_extension("GL_EXT_color_buffer_half_float") ||
+            crate::has_gl_extension("GL_EXT_color_buffer_float")
+        );
+        let (internal_format,ty)=if float{
+            (gl::RGBA16F,gl::HALF_FLOAT)
+        }else{
+            (gl::RGBA8,gl::UNSIGNED_BYTE)
+        };
+
+        //GL_MAX_SAMPLES isn't a limit every format reaches
+        let samples={
+            let mut max=max_samples(internal_format);
+            if depth_stencil{
+                max=max.min(max_samples(gl::DEPTH24_STENCIL8));
+            }
+            samples.min(max)
+        };
+
+        let mut fbo=0;
+        let mut texture=0;
+        let mut rb=0;
+        unsafe{
+            gl::GenTextures(1,&mut texture);
+            gl::BindTexture(gl::TEXTURE_2D,texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as GLint,
+                dim.x as GLsizei,
+                dim.y as GLsizei,
+                0,
+                gl::RGBA,
+                ty,
+                ptr::null()
+            );
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MIN_FILTER,gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MAG_FILTER,gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_WRAP_S,gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_WRAP_T,gl::CLAMP_TO_EDGE as GLint);
+
+            gl::GenFramebuffers(1,&mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER,fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER,gl::COLOR_ATTACHMENT0,gl::TEXTURE_2D,texture,0);
+            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER),gl::FRAMEBUFFER_COMPLETE);
+
+            let msaa=if samples>1{
+                let mut ms=Multisample{fbo:0,color:0,samples};
+                gl::GenRenderbuffers(1,&mut ms.color);
+                gl::BindRenderbuffer(gl::RENDERBUFFER,ms.color);
+                gl::RenderbufferStorageMultisample(gl::RENDERBUFFER,samples as GLsizei,internal_format,dim.x as GLsizei,dim.y as GLsizei);
+
+                gl::GenFramebuffers(1,&mut ms.fbo);
+                gl::BindFramebuffer(gl::FRAMEBUFFER,ms.fbo);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER,gl::COLOR_ATTACHMENT0,gl::RENDERBUFFER,ms.color);
+                Some(ms)
+            }else{
+                None
+            };
+
+            //attached to whichever framebuffer is drawn into, which is still bound
+            if depth_stencil{
+                gl::GenRenderbuffers(1,&mut rb);
+                gl::BindRenderbuffer(gl::RENDERBUFFER,rb);
+                //zero samples is a normal renderbuffer
+                let depth_samples=if msaa.is_some(){samples}else{0};
+                gl::RenderbufferStorageMultisample(gl::RENDERBUFFER,depth_samples as GLsizei,gl::DEPTH24_STENCIL8,dim.x as GLsizei,dim.y as GLsizei);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER,gl::DEPTH_STENCIL_ATTACHMENT,gl::RENDERBUFFER,rb);
+            }
+
+            assert_eq!(gl::CheckFramebufferStatus(gl::FRAMEBUFFER),gl::FRAMEBUFFER_COMPLETE);
+            gl::BindFramebuffer(gl::FRAMEBUFFER,0);
+            debug::check_error();
+
+            OffscreenTarget{fbo,texture,depth_stencil:rb,msaa,float,dim,ctx:context::current()}
+        }
+    }
+
+    ///Copies the multisampled renderbuffer into the texture.
+    ///Does nothing if the target is not multisampled.
+    pub fn resolve(&self){
+        if let Some(ms)=&self.msaa{
+            let (w,h)=(self.dim.x as GLint,self.dim.y as GLint);
+            unsafe{
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER,ms.fbo);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER,self.fbo);
+                gl::BlitFramebuffer(0,0,w,h,0,0,w,h,gl::COLOR_BUFFER_BIT,gl::NEAREST);
+                gl::BindFramebuffer(gl::FRAMEBUFFER,0);
+            }
+            debug::check_error();
+        }
+    }
+
+    ///Whether the texture holds half floats.
+    pub fn is_float(&self)->bool{
+        self.float
+    }
+
+    ///The number of samples, zero if not multisampled.
+    pub fn get_samples(&self)->usize{
+        self.msaa.as_ref().map(|ms|ms.samples).unwrap_or(0)
+    }
+
+    //The framebuffer draw sessions render into.
+    pub(crate) fn draw_fbo(&self)->GLuint{
+        self.msaa.as_ref().map(|ms|ms.fbo).unwrap_or(self.fbo)
+    }
+
+    ///Recreates the attachments if the size changed, or if the context was lost.
+    ///The contents are lost.
+    pub fn resize(&mut self,dim:Vec2<usize>){
+        if dim!=self.dim || !self.is_valid(){
+            *self=Self::create(dim,self.depth_stencil!=0,self.get_samples(),self.float);
+        }
+    }
+
+    pub fn get_dim(&self)->Vec2<usize>{
+        self.dim
+    }
+
+    ///False once the context the target was created in is lost. resize() recreates it.
+    pub fn is_valid(&self)->bool{
+        !context::is_lost(self.ctx)
+    }
+
+    ///If the target is multisampled, call resolve() before reading from the texture.
+    pub fn get_texture_id(&self)->u32{
+        self.texture
+    }
+
+    ///A viewport covering the whole target.
+    pub fn viewport(&self)->Viewport{
+        let mut v=Viewport::full(self.dim);
+        v.framebuffer=self.draw_fbo();
+        v
+    }
+
+    ///A viewport covering part of the target.
+    pub fn sub_viewport(&self,offset:Vec2<usize>,size:Vec2<usize>)->Viewport{
+        let mut v=Viewport::sub(self.dim,offset,size);
+        v.framebuffer=self.draw_fbo();
+        v
+    }
+}
+
+//The most samples a multisampled renderbuffer of the format can have.
+fn max_samples(internal_format:GLenum)->usize{
+    unsafe{
+        let mut num:GLint=0;
+        gl::GetInternalformativ(gl::RENDERBUFFER,internal_format,gl::NUM_SAMPLE_COUNTS,1,&mut num);
+        if num<=0{
+            return 0;
+        }
+        //sorted largest first
+        let mut counts=vec![0 as GLint;num as usize];
+        gl::GetInternalformativ(gl::RENDERBUFFER,internal_format,gl::SAMPLES,num,counts.as_mut_ptr());
+        counts[0].max(0) as usize
+    }
+}
+
+impl Drop for OffscreenTarget{
+    fn drop(&mut self){
+        context::delete(self.ctx,&[Object::Framebuffer(self.fbo),Object::Texture(self.texture)]);
+        if self.depth_stencil!=0{
+            context::delete(self.ctx,&[Object::Renderbuffer(self.depth_stencil)]);
+        }
+        if let Some(ms)=&self.msaa{
+            context::delete(self.ctx,&[Object::Framebuffer(ms.fbo),Object::Renderbuffer(ms.color)]);
+        }
+    }
+}
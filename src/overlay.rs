@@ -0,0 +1,203 @@
+use axgeom::*;
+use crate::shader::*;
+use crate::context::{self,Object};
+use crate::vbo::gen_buffer;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use crate::camera::*;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::font::SdfFont;
+use crate::text_program::*;
+use crate::stats::{self,FrameStats};
+use crate::profiler::GpuProfiler;
+use crate::state;
+use crate::postprocess::Uniform;
+use core::mem;
+
+use std::ptr;
+use std::time::{Duration,Instant};
+use std::collections::VecDeque;
+
+static VS_SRC: &'static str = "
+#version 300 es
+in vec2 position;
+uniform mat3 mmatrix;
+void main() {
+    gl_Position = vec4(mmatrix * vec3(position, 1.0), 1.0);
+}";
+
+static FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+uniform vec4 color;
+out vec4 out_color;
+void main() {
+    out_color = color;
+}";
+
+//The number of frames shown in the graph.
+const HISTORY:usize=120;
+//The graph is this many milliseconds tall.
+const GRAPH_MS:f32=50.0;
+
+
+
+
+//Solid rectangles for the panel and the frame time graph.
+struct RectProgram{
+    program:Program,
+    vbo:GLuint,
+}
+
+impl RectProgram{
+    fn new()->RectProgram{
+        RectProgram{program:Program::new("overlay RectProgram",VS_SRC,FS_SRC,None),vbo:gen_buffer()}
+    }
+
+    //rects are x,y,width,height in the view's coordinates.
+    fn draw(&mut self,view:&View,rects:&[[f32;4]],color:[f32;4]){
+        let mut verts:Vec<[f32;2]>=Vec::with_capacity(rects.len()*6);
+        for r in rects.iter(){
+            let (x0,y0,x1,y1)=(r[0],r[1],r[0]+r[2],r[1]+r[3]);
+            verts.extend_from_slice(&[[x0,y0],[x1,y0],[x0,y1],[x0,y1],[x1,y0],[x1,y1]]);
+        }
+
+        let program=self.program.get_id();
+        BlendMode::Alpha.apply();
+        unsafe{
+            state::use_program(program);
+
+            state::set_uniform(program,"mmatrix",Uniform::Mat3(view.matrix));
+
+            state::set_uniform(program,"color",Uniform::Vec4(color));
+
+            state::bind_array_buffer(self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (verts.len()*mem::size_of::<[f32;2]>()) as GLsizeiptr,
+                verts.as_ptr() as *const std::ffi::c_void,
+                gl::STREAM_DRAW,
+            );
+
+            let pos_attr = state::attrib_location(program,"position");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                2,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                0,
+                ptr::null(),
+            );
+
+            gl::DrawArrays(gl::TRIANGLES,0,verts.len() as i32);
+            stats::draw_call(verts.len());
+            debug::check_error();
+        }
+    }
+}
+
+impl Drop for RectProgram{
+    fn drop(&mut self){
+        context::delete(self.program.get_ctx(),&[Object::Buffer(self.vbo)]);
+    }
+}
+
+
+
+
+//Frame statistics drawn in the top left corner of the window, see GlSys::set_overlay().
+pub(crate) struct Overlay{
+    rects:RectProgram,
+    text:TextProgram,
+    font:SdfFont,
+    //cpu frame times in milliseconds, newest last
+    history:VecDeque<f32>,
+    last_frame:Option<Instant>,
+}
+
+impl Overlay{
+    pub(crate) fn new()->Overlay{
+        Overlay{
+            rects:RectProgram::new(),
+            text:TextProgram::new(),
+            font:SdfFont::builtin(),
+            history:VecDeque::with_capacity(HISTORY),
+            last_frame:None,
+        }
+    }
+
+    //Called once per frame right before the buffers are swapped.
+    //The gpu time is the sum of the profiler's passes. Waiting on the gpu
+    //to time it some other way would distort the frame times shown.
+    pub(crate) fn draw(&mut self,dim:Vec2<usize>,hidpi_factor:f32,stats:&FrameStats,profiler:&GpuProfiler){
+        let results=profiler.results();
+
+        let now=Instant::now();
+        if let Some(last)=self.last_frame{
+            if self.history.len()==HISTORY{
+                self.history.pop_front();
+            }
+            self.history.push_back(to_ms(now-last));
+        }
+        self.last_frame=Some(now);
+
+        let n=self.history.len().max(1) as f32;
+        let avg_ms=self.history.iter().sum::<f32>()/n;
+        let fps=if avg_ms>0.0{1000.0/avg_ms}else{0.0};
+
+        let s=hidpi_factor;
+        let line=13.0*s;
+        let pad=4.0*s;
+        let graph=vec2(HISTORY as f32*s,40.0*s);
+        let mut lines=vec![
+            format!("fps   {:.1}",fps),
+            format!("frame {:.2} ms",avg_ms),
+            if results.is_empty(){
+                "gpu   n/a".to_string()
+            }else{
+                format!("gpu   {:.2} ms",profiler.total())
+            },
+            format!("draws {}",stats.draw_calls),
+            format!("verts {}",stats.vertices_drawn),
+            format!("upld  {}",stats.vertices_uploaded),
+            format!("state {} ({} elided)",stats.state_changes,stats.state_changes_elided),
+        ];
+        for (name,ms) in results.iter(){
+            lines.push(format!("  {} {:.2} ms",name,ms));
+        }
+        let text_height=lines.len() as f32*line;
+        let panel=[0.0,0.0,graph.x+2.0*pad,text_height+graph.y+3.0*pad];
+
+        let viewport=Viewport::full(dim);
+        let camera=Camera2D::from_rect(Rect::new(0.0,dim.x as f32,0.0,dim.y as f32));
+        let view=viewport.begin_keep(&camera);
+
+        self.rects.draw(&view,&[panel],[0.0,0.0,0.0,0.6]);
+
+        let top=text_height+2.0*pad;
+        let bars:Vec<[f32;4]>=self.history.iter().enumerate().map(|(i,&ms)|{
+            let h=(ms/GRAPH_MS).min(1.0)*graph.y;
+            [pad+i as f32*s,top+graph.y-h,s,h]
+        }).collect();
+        self.rects.draw(&view,&bars,[0.3,0.9,0.4,1.0]);
+
+        //lines at 60 and 30 fps
+        let mark=|ms:f32|[pad,top+graph.y-ms/GRAPH_MS*graph.y,graph.x,s];
+        self.rects.draw(&view,&[mark(1000.0/60.0),mark(1000.0/30.0)],[1.0,1.0,1.0,0.4]);
+        Viewport::end();
+
+        let mut session=self.text.new_hud_session(&viewport);
+        let style=TextStyle{size:line,..TextStyle::default()};
+        for (i,l) in lines.iter().enumerate(){
+            session.draw_text(&self.font,l,vec2(pad,pad+i as f32*line),&style);
+        }
+    }
+}
+
+fn to_ms(d:Duration)->f32{
+    d.as_secs_f32()*1000.0
+}
@@ -0,0 +1,341 @@
+use axgeom::*;
+use crate::shader::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use crate::blit_program::FULLSCREEN_VS_SRC;
+use crate::offscreen::OffscreenTarget;
+use crate::program_cache::ProgramCache;
+use crate::viewport::Viewport;
+use crate::texture::*;
+use crate::stats;
+use crate::state;
+use crate::profiler;
+
+
+
+static THRESHOLD_FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform sampler2D tex0;
+uniform float threshold;
+out vec4 out_color;
+void main() {
+    vec3 c = texture(tex0, uv).rgb;
+    float l = max(c.r, max(c.g, c.b));
+    out_color = vec4(c * (max(l - threshold, 0.0) / max(l, 0.0001)), 1.0);
+}";
+
+static BLUR_FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform sampler2D tex0;
+uniform vec2 step;
+uniform float sigma;
+out vec4 out_color;
+void main() {
+    int radius = int(ceil(sigma * 3.0));
+    vec4 sum = vec4(0.0);
+    float total = 0.0;
+    for (int i = -radius; i <= radius; i++) {
+        float x = float(i);
+        float w = exp(-(x * x) / (2.0 * sigma * sigma));
+        sum += texture(tex0, uv + step * x) * w;
+        total += w;
+    }
+    out_color = sum / total;
+}";
+
+//tex0 is the scene, tex1 the blurred bright parts, tex2 the color grading lut.
+static COMPOSITE_FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform sampler2D tex0;
+uniform sampler2D tex1;
+uniform sampler2D tex2;
+uniform float bloom_intensity;
+uniform float vignette_strength;
+uniform float vignette_radius;
+uniform float lut_amount;
+uniform float lut_size;
+out vec4 out_color;
+
+vec3 grade(vec3 c) {
+    float n = lut_size;
+    float b = clamp(c.b, 0.0, 1.0) * (n - 1.0);
+    float s0 = floor(b);
+    float s1 = min(s0 + 1.0, n - 1.0);
+    float x = clamp(c.r, 0.0, 1.0) * (n - 1.0) + 0.5;
+    float y = (clamp(c.g, 0.0, 1.0) * (n - 1.0) + 0.5) / n;
+    vec3 a = texture(tex2, vec2((s0 * n + x) / (n * n), y)).rgb;
+    vec3 d = texture(tex2, vec2((s1 * n + x) / (n * n), y)).rgb;
+    return mix(a, d, b - s0);
+}
+
+void main() {
+    vec3 c = texture(tex0, uv).rgb;
+    c += texture(tex1, uv).rgb * bloom_intensity;
+
+    if (lut_amount > 0.0) {
+        c = mix(c, grade(c), lut_amount);
+    }
+
+    float d = distance(uv, vec2(0.5)) / 0.7071;
+    c *= 1.0 - vignette_strength * smoothstep(vignette_radius, 1.0, d);
+
+    out_color = vec4(c, 1.0);
+}";
+
+
+
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Uniform{
+    Int(i32),
+    Float(f32),
+    Vec2([f32;2]),
+    Vec3([f32;3]),
+    Vec4([f32;4]),
+    ///Column major.
+    Mat3([[f32;3];3]),
+}
+
+
+///A program drawn over a whole viewport.
+///
+///The fragment shader gets the texture coordinate as `in vec2 uv`
+///and the input textures as `uniform sampler2D tex0`, `tex1` and so on.
+pub struct FullscreenPass{
+    program:Program,
+}
+
+impl FullscreenPass{
+    pub fn new(fs_src:&str)->FullscreenPass{
+        Self::with_cache(fs_src,None)
+    }
+
+    ///Same as new() but tries to load the linked program from the cache first.
+    pub fn new_cached(fs_src:&str,cache:&ProgramCache)->FullscreenPass{
+        Self::with_cache(fs_src,Some(cache))
+    }
+
+    fn with_cache(fs_src:&str,cache:Option<&ProgramCache>)->FullscreenPass{
+        FullscreenPass{program:Program::new("FullscreenPass",FULLSCREEN_VS_SRC,fs_src,cache)}
+    }
+
+    ///Overwrites the area inside the viewport's border. Blending is disabled.
+    pub fn run(&mut self,viewport:&Viewport,textures:&[u32],uniforms:&[(&str,Uniform)]){
+        viewport.begin_inner();
+        state::set_blend(None);
+        self.draw_current(textures,uniforms);
+        Viewport::end();
+    }
+
+    //Draws into whatever framebuffer and gl viewport are currently set,
+    //with the current blend state.
+    pub(crate) fn draw_current(&mut self,textures:&[u32],uniforms:&[(&str,Uniform)]){
+        self.program.restore();
+        let program=self.program.get_id();
+        state::use_program(program);
+        unsafe{
+            for (i,&texture) in textures.iter().enumerate(){
+                gl::ActiveTexture(gl::TEXTURE0+i as GLenum);
+                gl::BindTexture(gl::TEXTURE_2D,texture);
+                state::set_uniform(program,&format!("tex{}",i),Uniform::Int(i as GLint));
+            }
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            for &(name,uniform) in uniforms.iter(){
+                state::set_uniform(program,name,uniform);
+            }
+            debug::check_error();
+
+            gl::DrawArrays(gl::TRIANGLE_STRIP,0,4);
+            stats::draw_call(4);
+            debug::check_error();
+        }
+    }
+}
+
+
+
+
+///A separable gaussian blur, done as a horizontal and a vertical pass.
+pub struct GaussianBlur{
+    pass:FullscreenPass
+}
+
+impl GaussianBlur{
+    pub fn new()->GaussianBlur{
+        GaussianBlur{pass:FullscreenPass::new(BLUR_FS_SRC)}
+    }
+
+    ///Blurs the target in place. tmp must have the same size.
+    ///sigma is in pixels of the target.
+    pub fn apply(&mut self,target:&OffscreenTarget,tmp:&OffscreenTarget,sigma:f32){
+        assert_eq!(target.get_dim(),tmp.get_dim());
+        let dim=target.get_dim();
+        let sigma=sigma.max(0.01);
+
+        self.pass.run(&tmp.viewport(),&[target.get_texture_id()],&[
+            ("step",Uniform::Vec2([1.0/dim.x as f32,0.0])),
+            ("sigma",Uniform::Float(sigma))
+        ]);
+        self.pass.run(&target.viewport(),&[tmp.get_texture_id()],&[
+            ("step",Uniform::Vec2([0.0,1.0/dim.y as f32])),
+            ("sigma",Uniform::Float(sigma))
+        ]);
+    }
+}
+
+
+
+
+///Glow around bright parts of the scene.
+#[derive(Copy,Clone,Debug)]
+pub struct Bloom{
+    ///Brightness (largest color channel) above which pixels glow.
+    pub threshold:f32,
+    pub intensity:f32,
+    ///Blur sigma in pixels of the half resolution bloom buffer.
+    pub radius:f32,
+}
+
+#[derive(Copy,Clone,Debug)]
+pub struct Vignette{
+    ///How dark the corners get, from 0 to 1.
+    pub strength:f32,
+    ///Where the darkening starts, from 0 at the center to 1 at the corners.
+    pub radius:f32,
+}
+
+///Which effects to apply to a frame.
+#[derive(Copy,Clone,Debug)]
+pub struct PostSettings{
+    ///If false the scene is copied to the output untouched.
+    pub enabled:bool,
+    pub bloom:Option<Bloom>,
+    pub vignette:Option<Vignette>,
+    ///How much of the lut set with PostProcess::set_lut() to blend in, from 0 to 1.
+    pub color_grade:Option<f32>,
+}
+
+impl Default for PostSettings{
+    fn default()->PostSettings{
+        PostSettings{enabled:true,bloom:None,vignette:None,color_grade:None}
+    }
+}
+
+
+///A post processing chain.
+///
+///Draw the scene into scene_viewport(), then call apply()
+///to draw it with the effects into the output viewport.
+pub struct PostProcess{
+    scene:OffscreenTarget,
+    bloom_a:OffscreenTarget,
+    bloom_b:OffscreenTarget,
+    threshold:FullscreenPass,
+    blur:GaussianBlur,
+    composite:FullscreenPass,
+    lut:Option<Texture2D>,
+    lut_size:usize,
+}
+
+impl PostProcess{
+    pub fn new(dim:Vec2<usize>)->PostProcess{
+        Self::with_samples(dim,0)
+    }
+
+    ///Draws the scene into a multisampled target.
+    pub fn with_samples(dim:Vec2<usize>,samples:usize)->PostProcess{
+        let half=Self::half(dim);
+        PostProcess{
+            scene:OffscreenTarget::new_multisampled(dim,samples,false),
+            bloom_a:OffscreenTarget::new(half),
+            bloom_b:OffscreenTarget::new(half),
+            threshold:FullscreenPass::new(THRESHOLD_FS_SRC),
+            blur:GaussianBlur::new(),
+            composite:FullscreenPass::new(COMPOSITE_FS_SRC),
+            lut:None,
+            lut_size:0
+        }
+    }
+
+    fn half(dim:Vec2<usize>)->Vec2<usize>{
+        vec2((dim.x/2).max(1),(dim.y/2).max(1))
+    }
+
+    ///Sets the color grading lut.
+    ///It is a size*size*size cube laid out as size slices side by side,
+    ///so the rgba data is size*size texels wide and size texels high.
+    ///Texel (b*size+r,g) holds the graded color for input color (r,g,b).
+    ///It is dropped if the context is lost, call it again from App::context_recovered().
+    pub fn set_lut(&mut self,size:usize,data:&[u8]){
+        assert_eq!(data.len(),size*size*size*4);
+        self.lut=Some(Texture2D::from_rgba(vec2(size*size,size),data,TextureOptions::default()));
+        self.lut_size=size;
+    }
+
+    ///The viewport to draw the scene into.
+    ///The buffers are resized to dim first, so pass the output size every frame.
+    ///They are recreated if the context was lost.
+    pub fn scene_viewport(&mut self,dim:Vec2<usize>)->Viewport{
+        if dim!=self.scene.get_dim() || !self.scene.is_valid(){
+            self.scene.resize(dim);
+            self.bloom_a.resize(Self::half(dim));
+            self.bloom_b.resize(Self::half(dim));
+        }
+        self.scene.viewport()
+    }
+
+    ///Draws the scene with the effects into the output viewport.
+    pub fn apply(&mut self,settings:&PostSettings,output:&Viewport){
+        let mut bloom_intensity=0.0;
+        let mut vignette=(0.0,0.0);
+        let mut lut_amount=0.0;
+
+        self.scene.resolve();
+
+        //lost with the context, set_lut() has to be called again
+        if self.lut.as_ref().map(|t|!t.is_valid()).unwrap_or(false){
+            self.lut=None;
+        }
+
+        if settings.enabled{
+            if let Some(bloom)=settings.bloom{
+                let scope=profiler::scope("bloom threshold");
+                self.threshold.run(&self.bloom_a.viewport(),&[self.scene.get_texture_id()],&[
+                    ("threshold",Uniform::Float(bloom.threshold))
+                ]);
+                drop(scope);
+                let _scope=profiler::scope("bloom blur");
+                self.blur.apply(&self.bloom_a,&self.bloom_b,bloom.radius);
+                bloom_intensity=bloom.intensity;
+            }
+            if let Some(v)=settings.vignette{
+                //smoothstep is undefined if both edges are equal
+                vignette=(v.strength,v.radius.min(0.99));
+            }
+            if let Some(amount)=settings.color_grade{
+                if self.lut.is_some(){
+                    lut_amount=amount;
+                }
+            }
+        }
+
+        let _scope=profiler::scope("composite");
+        let lut=self.lut.as_ref().map(|t|t.get_id()).unwrap_or(0);
+        self.composite.run(output,&[self.scene.get_texture_id(),self.bloom_a.get_texture_id(),lut],&[
+            ("bloom_intensity",Uniform::Float(bloom_intensity)),
+            ("vignette_strength",Uniform::Float(vignette.0)),
+            ("vignette_radius",Uniform::Float(vignette.1)),
+            ("lut_amount",Uniform::Float(lut_amount)),
+            ("lut_size",Uniform::Float(self.lut_size as f32))
+        ]);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+use crate::context::{self,ContextId,Object};
+
+use std::collections::VecDeque;
+use std::cell::RefCell;
+use std::rc::{Rc,Weak};
+
+//Frames waiting for their results. Any older and their queries are thrown away.
+const MAX_PENDING:usize=6;
+
+
+struct Frame{
+    queries:Vec<(String,GLuint)>,
+}
+
+
+///Measures how long named passes take on the gpu, using EXT_disjoint_timer_query.
+///
+///Results are read back a few frames later once the gpu has them ready,
+///so measuring never stalls the pipeline. Without the extension every
+///scope does nothing and there are no results.
+///
+///Scopes can't nest. Call end_frame() once per frame, GlSys::swap_buffers()
+///does this for the profiler returned by GlSys::gpu_profiler().
+///
+///That profiler also times the crate's own draw calls and post processing
+///passes, under names like "circles" or "bloom blur", unless one of its
+///scopes is already open.
+pub struct GpuProfiler{
+    inner:Rc<RefCell<Inner>>,
+}
+
+struct Inner{
+    supported:bool,
+    free:Vec<GLuint>,
+    current:Frame,
+    pending:VecDeque<Frame>,
+    active:bool,
+    results:Vec<(String,f32)>,
+    ctx:ContextId,
+}
+
+impl GpuProfiler{
+    pub fn new()->GpuProfiler{
+        let supported=crate::has_gl_extension("GL_EXT_disjoint_timer_query") && gl::GetQueryObjectui64vEXT::is_loaded();
+        if supported{
+            //reading the flag clears it
+            disjoint();
+        }
+        let inner=Inner{
+            supported,
+            free:Vec::new(),
+            current:Frame{queries:Vec::new()},
+            pending:VecDeque::new(),
+            active:false,
+            results:Vec::new(),
+            ctx:context::current(),
+        };
+        GpuProfiler{inner:Rc::new(RefCell::new(inner))}
+    }
+
+    //Makes the built in scopes go to this profiler.
+    pub(crate) fn make_current(&self){
+        CURRENT.with(|c|*c.borrow_mut()=Rc::downgrade(&self.inner));
+    }
+
+    pub fn is_supported(&self)->bool{
+        self.inner.borrow().supported
+    }
+
+    ///Times the gl calls made until the returned scope is dropped.
+    ///Several scopes with the same name in a frame are added together.
+    pub fn scope(&mut self,name:&str)->GpuScope{
+        let mut inner=self.inner.borrow_mut();
+        assert!(!inner.active,"gpu profiler scopes can't nest");
+        inner.begin(name);
+        drop(inner);
+        GpuScope{a:self}
+    }
+
+    ///Finishes the frame and collects the results of earlier frames that are ready.
+    pub fn end_frame(&mut self){
+        self.inner.borrow_mut().end_frame();
+    }
+
+    ///Milliseconds per pass name, in the order the passes ran,
+    ///for the newest frame whose results are ready.
+    pub fn results(&self)->Vec<(String,f32)>{
+        self.inner.borrow().results.clone()
+    }
+
+    pub fn get(&self,name:&str)->Option<f32>{
+        self.inner.borrow().results.iter().find(|(n,_)|n==name).map(|(_,t)|*t)
+    }
+
+    ///The sum of all passes of the newest frame whose results are ready.
+    pub fn total(&self)->f32{
+        self.inner.borrow().results.iter().map(|(_,t)|t).sum()
+    }
+}
+
+impl Inner{
+    fn begin(&mut self,name:&str){
+        if self.supported{
+            let query=match self.free.pop(){
+                Some(q)=>q,
+                None=>{
+                    let mut q=0;
+                    unsafe{
+                        gl::GenQueriesEXT(1,&mut q);
+                    }
+                    q
+                }
+            };
+            unsafe{
+                gl::BeginQueryEXT(gl::TIME_ELAPSED_EXT,query);
+            }
+            self.current.queries.push((name.to_string(),query));
+            self.active=true;
+        }
+    }
+
+    fn end(&mut self){
+        if self.active{
+            unsafe{
+                gl::EndQueryEXT(gl::TIME_ELAPSED_EXT);
+            }
+            self.active=false;
+        }
+    }
+
+    fn end_frame(&mut self){
+        assert!(!self.active,"a gpu profiler scope is still open");
+        if !self.supported{
+            return;
+        }
+        let frame=std::mem::replace(&mut self.current,Frame{queries:Vec::new()});
+        if !frame.queries.is_empty(){
+            self.pending.push_back(frame);
+        }
+
+        //Everything measured while the gpu was disjoint is meaningless.
+        if disjoint(){
+            while let Some(f)=self.pending.pop_front(){
+                self.recycle(f);
+            }
+            return;
+        }
+
+        //Frames finish in order, so stop at the first that isn't ready.
+        while let Some(f)=self.pending.front(){
+            let &(_,last)=f.queries.last().unwrap();
+            let mut available:GLuint=0;
+            unsafe{
+                gl::GetQueryObjectuivEXT(last,gl::QUERY_RESULT_AVAILABLE_EXT,&mut available);
+            }
+            if available==0{
+                break;
+            }
+
+            let f=self.pending.pop_front().unwrap();
+            self.results.clear();
+            for (name,query) in f.queries.iter(){
+                let mut ns:GLuint64=0;
+                unsafe{
+                    gl::GetQueryObjectui64vEXT(*query,gl::QUERY_RESULT_EXT,&mut ns);
+                }
+                let ms=ns as f32/1_000_000.0;
+                match self.results.iter_mut().find(|(n,_)|n==name){
+                    Some((_,t))=>*t+=ms,
+                    None=>self.results.push((name.clone(),ms))
+                }
+            }
+            self.recycle(f);
+        }
+
+        while self.pending.len()>MAX_PENDING{
+            let f=self.pending.pop_front().unwrap();
+            for (_,query) in f.queries.iter(){
+                unsafe{
+                    gl::DeleteQueriesEXT(1,query);
+                }
+            }
+        }
+        debug::check_error();
+    }
+
+    fn recycle(&mut self,frame:Frame){
+        self.free.extend(frame.queries.into_iter().map(|(_,q)|q));
+    }
+}
+
+impl Drop for Inner{
+    fn drop(&mut self){
+        let mut queries=std::mem::replace(&mut self.free,Vec::new());
+        for f in self.pending.drain(..).chain(std::iter::once(std::mem::replace(&mut self.current,Frame{queries:Vec::new()}))){
+            queries.extend(f.queries.into_iter().map(|(_,q)|q));
+        }
+        let queries:Vec<Object>=queries.into_iter().map(Object::Query).collect();
+        context::delete(self.ctx,&queries);
+    }
+}
+
+fn disjoint()->bool{
+    let mut d:GLint=0;
+    unsafe{
+        gl::GetIntegerv(gl::GPU_DISJOINT_EXT,&mut d);
+    }
+    d!=0
+}
+
+
+
+
+///Ends its timer query when dropped.
+pub struct GpuScope<'a>{
+    a:&'a mut GpuProfiler,
+}
+
+impl<'a> Drop for GpuScope<'a>{
+    fn drop(&mut self){
+        self.a.inner.borrow_mut().end();
+    }
+}
+
+
+
+thread_local!{
+    static CURRENT:RefCell<Weak<RefCell<Inner>>>=const{RefCell::new(Weak::new())};
+}
+
+//Times the crate's own drawing with the profiler of GlSys, if it has no scope open.
+pub(crate) fn scope(name:&str)->Option<BuiltinScope>{
+    let inner=CURRENT.with(|c|c.borrow().upgrade())?;
+    {
+        let mut i=inner.borrow_mut();
+        if i.active || !i.supported{
+            return None;
+        }
+        i.begin(name);
+    }
+    Some(BuiltinScope{inner})
+}
+
+pub(crate) struct BuiltinScope{
+    inner:Rc<RefCell<Inner>>,
+}
+
+impl Drop for BuiltinScope{
+    fn drop(&mut self){
+        self.inner.borrow_mut().end();
+    }
+}
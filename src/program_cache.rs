@@ -0,0 +1,159 @@
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use std::ffi::CStr;
+use std::fs;
+use std::path::PathBuf;
+
+
+
+
+///An on-disk cache of linked program binaries.
+///
+///Entries are keyed by a hash of the shader sources together with the
+///vendor, renderer and version strings of the current driver,
+///so a driver update or a different gpu simply misses the cache.
+///Every failure (io or a binary the driver rejects) is treated as a miss
+///and the program is compiled from source as usual.
+#[derive(Clone)]
+pub struct ProgramCache{
+    dir:PathBuf
+}
+
+impl ProgramCache{
+    ///The directory is created the first time a binary is stored.
+    pub fn new<P:Into<PathBuf>>(dir:P)->ProgramCache{
+        ProgramCache{dir:dir.into()}
+    }
+
+    fn supported()->bool{
+        let mut num_formats:GLint=0;
+        unsafe{
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS,&mut num_formats);
+        }
+        num_formats>0
+    }
+
+    fn entry_path(&self,vs_src:&str,fs_src:&str)->PathBuf{
+        let mut hash=Fnv1a::new();
+        hash.write(vs_src.as_bytes());
+        hash.write(&[0]);
+        hash.write(fs_src.as_bytes());
+        for &name in [gl::VENDOR,gl::RENDERER,gl::VERSION].iter(){
+            hash.write(&[0]);
+            hash.write(get_gl_string(name).as_bytes());
+        }
+        self.dir.join(format!("{:016x}.bin",hash.finish()))
+    }
+
+    ///Returns a linked program, or None if there was no usable binary.
+    pub(crate) fn load(&self,vs_src:&str,fs_src:&str)->Option<GLuint>{
+        if !Self::supported(){
+            return None;
+        }
+
+        let path=self.entry_path(vs_src,fs_src);
+        let data=fs::read(&path).ok()?;
+        if data.len()<=4{
+            return None;
+        }
+        let format=u32::from_le_bytes([data[0],data[1],data[2],data[3]]);
+        let binary=&data[4..];
+
+        unsafe{
+            let program=gl::CreateProgram();
+            //so the error checked below can only come from ProgramBinary
+            debug::flush_errors();
+            gl::ProgramBinary(
+                program,
+                format as GLenum,
+                binary.as_ptr() as *const std::ffi::c_void,
+                binary.len() as GLsizei
+            );
+            //an unknown format raises an error, other rejected binaries fail to link.
+            //Both are cache misses rather than bugs.
+            let rejected=gl::GetError()!=gl::NO_ERROR;
+
+            let mut status = gl::FALSE as GLint;
+            gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+            if rejected || status != (gl::TRUE as GLint){
+                gl::DeleteProgram(program);
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+            Some(program)
+        }
+    }
+
+    ///Best effort. The program must have been linked with
+    ///PROGRAM_BINARY_RETRIEVABLE_HINT set.
+    pub(crate) fn store(&self,vs_src:&str,fs_src:&str,program:GLuint){
+        if !Self::supported(){
+            return;
+        }
+
+        let mut len:GLint=0;
+        let mut format:GLenum=0;
+        let mut data;
+        unsafe{
+            gl::GetProgramiv(program,gl::PROGRAM_BINARY_LENGTH,&mut len);
+            if len<=0{
+                return;
+            }
+            data=vec![0u8;4+len as usize];
+            let mut written:GLsizei=0;
+            debug::flush_errors();
+            gl::GetProgramBinary(
+                program,
+                len,
+                &mut written,
+                &mut format,
+                data[4..].as_mut_ptr() as *mut std::ffi::c_void
+            );
+            if gl::GetError()!=gl::NO_ERROR{
+                return;
+            }
+            data.truncate(4+written as usize);
+        }
+        data[..4].copy_from_slice(&(format as u32).to_le_bytes());
+
+        let path=self.entry_path(vs_src,fs_src);
+        //unique per process, so two processes storing the same entry don't write into one file
+        let tmp=path.with_extension(format!("{}.tmp",std::process::id()));
+        let _ = fs::create_dir_all(&self.dir)
+            .and_then(|_|fs::write(&tmp,&data))
+            .and_then(|_|fs::rename(&tmp,&path));
+    }
+}
+
+
+fn get_gl_string(name:GLenum)->String{
+    unsafe{
+        let s=gl::GetString(name);
+        if s.is_null(){
+            String::new()
+        }else{
+            CStr::from_ptr(s as *const _).to_string_lossy().into_owned()
+        }
+    }
+}
+
+
+//The std hasher is not guaranteed to be stable between compiler versions,
+//and these hashes end up on disk.
+struct Fnv1a(u64);
+impl Fnv1a{
+    fn new()->Fnv1a{
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+    fn write(&mut self,bytes:&[u8]){
+        for &b in bytes{
+            self.0^=u64::from(b);
+            self.0=self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    fn finish(&self)->u64{
+        self.0
+    }
+}
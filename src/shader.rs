@@ -1,5 +1,8 @@
 use crate::gl;
 use crate::gl::types::*;
+use crate::program_cache::ProgramCache;
+use crate::debug;
+use crate::context::{self, ContextId, Object};
 use std::ptr;
 use std::str;
 use std::ffi::CString;
@@ -30,20 +33,23 @@ pub fn compile_shader(src: &str, ty: GLenum) -> GLuint {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ShaderInfoLog not valid utf8")
-            );
+            let info = str::from_utf8(&buf)
+                .ok()
+                .expect("ShaderInfoLog not valid utf8");
+            let kind = if ty == gl::VERTEX_SHADER { "vertex" } else { "fragment" };
+            log::error!(target: "gl::shader", kind = kind, info = info; "shader failed to compile");
+            panic!("{}", info);
         }
     }
     shader
 }
 
-pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
+pub fn link_program(vs: GLuint, fs: GLuint, retrievable: bool) -> GLuint {
     unsafe {
         let program = gl::CreateProgram();
+        if retrievable {
+            gl::ProgramParameteri(program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+        }
         gl::AttachShader(program, vs);
         gl::AttachShader(program, fs);
         gl::LinkProgram(program);
@@ -63,14 +69,102 @@ pub fn link_program(vs: GLuint, fs: GLuint) -> GLuint {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
-            panic!(
-                "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ProgramInfoLog not valid utf8")
-            );
+            let info = str::from_utf8(&buf)
+                .ok()
+                .expect("ProgramInfoLog not valid utf8");
+            log::error!(target: "gl::shader", program = program, info = info; "program failed to link");
+            panic!("{}", info);
         }
         program
     }
 }
 
+
+///Returns (program,vs,fs). The name labels the program in KHR_debug messages.
+///If the program was loaded from the cache there are no shader objects
+///and vs and fs are zero, which glDeleteShader silently ignores.
+pub fn build_program(name: &str, vs_src: &str, fs_src: &str, cache: Option<&ProgramCache>) -> (GLuint, GLuint, GLuint) {
+    if let Some(cache) = cache {
+        if let Some(program) = cache.load(vs_src, fs_src) {
+            debug::label(gl::PROGRAM_KHR, program, name);
+            log::debug!(target:"gl::lifecycle", name=name, program=program; "loaded program from the cache");
+            return (program, 0, 0);
+        }
+    }
+
+    let vs = compile_shader(vs_src, gl::VERTEX_SHADER);
+    debug::check_error();
+
+    let fs = compile_shader(fs_src, gl::FRAGMENT_SHADER);
+    debug::check_error();
+
+    let program = link_program(vs, fs, cache.is_some());
+    debug::check_error();
+
+    if let Some(cache) = cache {
+        cache.store(vs_src, fs_src, program);
+    }
+    debug::label(gl::PROGRAM_KHR, program, name);
+    log::debug!(target:"gl::lifecycle", name=name, program=program; "linked program");
+    (program, vs, fs)
+}
+
+
+///A linked program and its shaders, tied to the context it was built in.
+///Keeps its sources and cache so restore() can build it again after the context is lost.
+pub(crate) struct Program {
+    id: GLuint,
+    vs: GLuint,
+    fs: GLuint,
+    ctx: ContextId,
+    name: &'static str,
+    vs_src: String,
+    fs_src: String,
+    cache: Option<ProgramCache>,
+}
+
+impl Program {
+    pub(crate) fn new(name: &'static str, vs_src: &str, fs_src: &str, cache: Option<&ProgramCache>) -> Program {
+        let (id, vs, fs) = build_program(name, vs_src, fs_src, cache);
+        Program {
+            id,
+            vs,
+            fs,
+            ctx: context::current(),
+            name,
+            vs_src: vs_src.to_string(),
+            fs_src: fs_src.to_string(),
+            cache: cache.cloned(),
+        }
+    }
+
+    pub(crate) fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    pub(crate) fn get_ctx(&self) -> ContextId {
+        self.ctx
+    }
+
+    //Builds the program again if the context it belongs to was lost.
+    //Returns whether it did, so objects made alongside it can be made again too.
+    pub(crate) fn restore(&mut self) -> bool {
+        if !context::is_stale(self.ctx) {
+            return false;
+        }
+        let (id, vs, fs) = build_program(self.name, &self.vs_src, &self.fs_src, self.cache.as_ref());
+        //the old objects went with their context
+        self.id = id;
+        self.vs = vs;
+        self.fs = fs;
+        self.ctx = context::current();
+        true
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        log::debug!(target:"gl::lifecycle", name=self.name, program=self.id; "dropping program");
+        context::delete(self.ctx, &[Object::Program(self.id), Object::Shader(self.fs), Object::Shader(self.vs)]);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::shader::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use crate::vbo::Buffer;
+use crate::program_cache::ProgramCache;
+use crate::camera::*;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::atlas::*;
+use crate::stats;
+use crate::state;
+use crate::profiler;
+use crate::postprocess::Uniform;
+use core::mem;
+
+use std::ptr;
+
+//The point is grown so the corners of a rotated sprite still fit inside it.
+static VS_SRC: &'static str = "
+#version 300 es
+in vec2 position;
+in float rotation;
+in uint sprite;
+uniform mat3 mmatrix;
+uniform float point_size;
+uniform float view_rotation;
+uniform highp sampler2D rects;
+flat out vec4 rect;
+flat out vec2 rot;
+void main() {
+    gl_PointSize = point_size * 1.41421356;
+    gl_Position = vec4(mmatrix * vec3(position, 1.0), 1.0);
+    int i = int(sprite);
+    rect = texelFetch(rects, ivec2(i % 256, i / 256), 0);
+    float r = rotation + view_rotation;
+    rot = vec2(cos(r), sin(r));
+}";
+
+static FS_SRC: &'static str = "
+#version 300 es
+precision highp float;
+flat in vec4 rect;
+flat in vec2 rot;
+uniform sampler2D atlas;
+uniform vec4 tint;
+out vec4 out_color;
+void main() {
+    vec2 p = (gl_PointCoord - vec2(0.5)) * 1.41421356;
+    vec2 q = vec2(rot.x * p.x + rot.y * p.y, -rot.y * p.x + rot.x * p.y) + vec2(0.5);
+    if (q.x < 0.0 || q.x > 1.0 || q.y < 0.0 || q.y > 1.0)
+        discard;
+    out_color = texture(atlas, rect.xy + q * rect.zw) * tint;
+}";
+
+
+
+
+///A point drawn as one sprite of a SpriteAtlas.
+#[repr(C)]
+#[derive(Copy,Clone,Debug,Default)]
+pub struct SpriteVertex{
+    pub pos:[f32;2],
+    ///In radians, clockwise on screen since the y axis points down.
+    pub rotation:f32,
+    ///The index returned by AtlasBuilder::add().
+    pub sprite:u32,
+}
+
+
+///Draws many different sprites from one atlas in a single draw call.
+pub struct SpriteProgram{
+    program:Program,
+}
+
+impl SpriteProgram{
+    pub fn new()->SpriteProgram{
+        Self::with_cache(None)
+    }
+
+    ///Same as new() but tries to load the linked program from the cache first.
+    pub fn new_cached(cache:&ProgramCache)->SpriteProgram{
+        Self::with_cache(Some(cache))
+    }
+
+    fn with_cache(cache:Option<&ProgramCache>)->SpriteProgram{
+        assert_eq!(RECTS_PER_ROW,256);
+        SpriteProgram{program:Program::new("SpriteProgram",VS_SRC,FS_SRC,cache)}
+    }
+
+    ///Clears the viewport and sets it up for drawing with the camera.
+    pub fn new_draw_session(&mut self,back_color:[f32;3],camera:&Camera2D,viewport:&Viewport)->SpriteSession{
+        self.program.restore();
+        let view=viewport.begin(camera,back_color);
+        SpriteSession{a:self,view}
+    }
+}
+
+
+
+
+pub struct SpriteSession<'a>{
+    a:&'a mut SpriteProgram,
+    view:View,
+}
+
+impl<'a> Drop for SpriteSession<'a>{
+    fn drop(&mut self){
+        Viewport::end();
+    }
+}
+
+impl<'a> SpriteSession<'a>{
+    ///The view this session draws with.
+    pub fn view(&self)->&View{
+        &self.view
+    }
+
+    ///size is the world width and height of every sprite.
+    ///The sprite colors are multiplied by tint.
+    pub fn draw_vbo_section(&mut self,buffer:&Buffer<SpriteVertex>,start:usize,end:usize,atlas:&SpriteAtlas,size:f32,tint:[f32;4],blend:BlendMode){
+        let _scope=profiler::scope("sprites");
+        let program=self.a.program.get_id();
+        blend.apply();
+        unsafe{
+            state::use_program(program);
+
+            state::set_uniform(program,"mmatrix",Uniform::Mat3(self.view.matrix));
+
+            state::set_uniform(program,"point_size",Uniform::Float(size*self.view.scale.x));
+
+            state::set_uniform(program,"view_rotation",Uniform::Float(-self.view.rotation));
+
+            state::set_uniform(program,"tint",Uniform::Vec4(tint));
+
+            gl::ActiveTexture(gl::TEXTURE1);
+            gl::BindTexture(gl::TEXTURE_2D,atlas.get_rect_texture_id());
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D,atlas.get_texture().get_id());
+
+            state::set_uniform(program,"atlas",Uniform::Int(0));
+            state::set_uniform(program,"rects",Uniform::Int(1));
+            debug::check_error();
+
+            state::bind_array_buffer(buffer.get_id());
+            let stride=mem::size_of::<SpriteVertex>() as i32;
+
+            let pos_attr = state::attrib_location(program,"position");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                2,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                stride,
+                ptr::null(),
+            );
+
+            let pos_attr = state::attrib_location(program,"rotation");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                1,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                stride,
+                (2*mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+
+            let pos_attr = state::attrib_location(program,"sprite");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribIPointer(
+                pos_attr as GLuint,
+                1,
+                gl::UNSIGNED_INT,
+                stride,
+                (3*mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+
+            gl::DrawArrays(gl::POINTS,start as i32,(end-start) as i32);
+            stats::draw_call(end-start);
+            debug::check_error();
+        }
+    }
+}
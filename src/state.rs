@@ -0,0 +1,169 @@
+//Tracks the gl state the crate sets so calls that wouldn't change anything can be skipped.
+//
+//Everything that binds a program or array buffer, changes blending
+//or sets a uniform has to go through here, or the cache goes stale.
+
+use crate::gl;
+use crate::gl::types::*;
+use crate::postprocess::Uniform;
+use crate::stats;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CString;
+
+
+
+
+#[derive(Default)]
+struct State{
+    program:GLuint,
+    array_buffer:GLuint,
+    //None until first set. The inner None is blending disabled.
+    blend:Option<Option<(GLenum,GLenum)>>,
+    uniform_locations:HashMap<(GLuint,String),GLint>,
+    attrib_locations:HashMap<(GLuint,String),GLint>,
+    uniforms:HashMap<(GLuint,GLint),Uniform>,
+}
+
+thread_local!{
+    static STATE:RefCell<State>=RefCell::new(State::default());
+}
+
+fn with<T>(func:impl FnOnce(&mut State)->T)->T{
+    STATE.with(|s|func(&mut s.borrow_mut()))
+}
+
+
+pub(crate) fn use_program(program:GLuint){
+    with(|s|{
+        if s.program==program{
+            stats::elided();
+        }else{
+            unsafe{
+                gl::UseProgram(program);
+            }
+            s.program=program;
+            stats::state_change();
+        }
+    });
+}
+
+pub(crate) fn bind_array_buffer(buffer:GLuint){
+    with(|s|{
+        if s.array_buffer==buffer{
+            stats::elided();
+        }else{
+            unsafe{
+                gl::BindBuffer(gl::ARRAY_BUFFER,buffer);
+            }
+            s.array_buffer=buffer;
+            stats::state_change();
+        }
+    });
+}
+
+//None disables blending.
+pub(crate) fn set_blend(func:Option<(GLenum,GLenum)>){
+    with(|s|{
+        if s.blend==Some(func){
+            stats::elided();
+            return;
+        }
+        unsafe{
+            match func{
+                Some((src,dst))=>{
+                    if s.blend.map(|b|b.is_none()).unwrap_or(true){
+                        gl::Enable(gl::BLEND);
+                    }
+                    gl::BlendFunc(src,dst);
+                },
+                None=>gl::Disable(gl::BLEND)
+            }
+        }
+        s.blend=Some(func);
+        stats::state_change();
+    });
+}
+
+pub(crate) fn uniform_location(program:GLuint,name:&str)->GLint{
+    with(|s|{
+        if let Some(&loc)=s.uniform_locations.get(&(program,name.to_string())){
+            stats::elided();
+            return loc;
+        }
+        let loc=unsafe{gl::GetUniformLocation(program,CString::new(name).unwrap().as_ptr())};
+        s.uniform_locations.insert((program,name.to_string()),loc);
+        loc
+    })
+}
+
+pub(crate) fn attrib_location(program:GLuint,name:&str)->GLint{
+    with(|s|{
+        if let Some(&loc)=s.attrib_locations.get(&(program,name.to_string())){
+            stats::elided();
+            return loc;
+        }
+        let loc=unsafe{gl::GetAttribLocation(program,CString::new(name).unwrap().as_ptr())};
+        s.attrib_locations.insert((program,name.to_string()),loc);
+        loc
+    })
+}
+
+//Binds the program and sets one of its uniforms.
+pub(crate) fn set_uniform(program:GLuint,name:&str,value:Uniform){
+    let loc=uniform_location(program,name);
+    use_program(program);
+    if loc<0{
+        return;
+    }
+    with(|s|{
+        if s.uniforms.get(&(program,loc))==Some(&value){
+            stats::elided();
+            return;
+        }
+        unsafe{
+            match value{
+                Uniform::Int(a)=>gl::Uniform1i(loc,a),
+                Uniform::Float(a)=>gl::Uniform1f(loc,a),
+                Uniform::Vec2(a)=>gl::Uniform2fv(loc,1,a.as_ptr()),
+                Uniform::Vec3(a)=>gl::Uniform3fv(loc,1,a.as_ptr()),
+                Uniform::Vec4(a)=>gl::Uniform4fv(loc,1,a.as_ptr()),
+                Uniform::Mat3(a)=>gl::UniformMatrix3fv(loc,1,gl::FALSE,a[0].as_ptr()),
+            }
+        }
+        s.uniforms.insert((program,loc),value);
+        stats::state_change();
+    });
+}
+
+//Forgets everything, for when the context changes.
+pub(crate) fn reset(){
+    with(|s|*s=State::default());
+}
+
+//Ids are reused by gl, so everything known about a deleted program has to go.
+pub(crate) fn delete_program(program:GLuint){
+    with(|s|{
+        if s.program==program{
+            s.program=0;
+        }
+        s.uniform_locations.retain(|(p,_),_|*p!=program);
+        s.attrib_locations.retain(|(p,_),_|*p!=program);
+        s.uniforms.retain(|(p,_),_|*p!=program);
+    });
+    unsafe{
+        gl::DeleteProgram(program);
+    }
+}
+
+pub(crate) fn delete_buffer(buffer:GLuint){
+    with(|s|{
+        if s.array_buffer==buffer{
+            s.array_buffer=0;
+        }
+    });
+    unsafe{
+        gl::DeleteBuffers(1,&buffer);
+    }
+}
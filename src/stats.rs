@@ -0,0 +1,55 @@
+use std::cell::Cell;
+
+
+
+
+///What the crate did with gl during one frame.
+#[derive(Copy,Clone,Debug,Default)]
+pub struct FrameStats{
+    pub draw_calls:usize,
+    ///Vertices drawn by all the draw calls.
+    pub vertices_drawn:usize,
+    ///Vertices sent to the gpu by Buffer::update().
+    pub vertices_uploaded:usize,
+    ///Program and buffer binds, blend changes, uniform uploads.
+    pub state_changes:usize,
+    ///Gl calls skipped because the state was already set,
+    ///including uniform and attribute location lookups.
+    pub state_changes_elided:usize,
+}
+
+thread_local!{
+    static CURRENT:Cell<FrameStats>=Cell::new(FrameStats::default());
+}
+
+fn modify(func:impl FnOnce(&mut FrameStats)){
+    CURRENT.with(|c|{
+        let mut s=c.get();
+        func(&mut s);
+        c.set(s);
+    });
+}
+
+pub(crate) fn draw_call(vertices:usize){
+    modify(|s|{
+        s.draw_calls+=1;
+        s.vertices_drawn+=vertices;
+    });
+}
+
+pub(crate) fn upload(vertices:usize){
+    modify(|s|s.vertices_uploaded+=vertices);
+}
+
+pub(crate) fn state_change(){
+    modify(|s|s.state_changes+=1);
+}
+
+pub(crate) fn elided(){
+    modify(|s|s.state_changes_elided+=1);
+}
+
+//Returns the counts so far and starts counting the next frame.
+pub(crate) fn take()->FrameStats{
+    CURRENT.with(|c|c.replace(FrameStats::default()))
+}
@@ -0,0 +1,287 @@
+use axgeom::*;
+use crate::shader::*;
+use crate::context::{self,Object};
+use crate::vbo::gen_buffer;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+
+use crate::program_cache::ProgramCache;
+use crate::camera::*;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+use crate::font::SdfFont;
+use crate::stats;
+use crate::state;
+use crate::postprocess::Uniform;
+use core::mem;
+
+use std::ptr;
+
+static VS_SRC: &'static str = "
+#version 300 es
+in vec2 position;
+in vec2 texcoord;
+uniform mat3 mmatrix;
+out vec2 uv;
+void main() {
+    gl_Position = vec4(mmatrix * vec3(position, 1.0), 1.0);
+    uv = texcoord;
+}";
+
+//The distance field is 0.5 on the edge of the glyph.
+//fwidth keeps the edge about one pixel wide at any size.
+static FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform sampler2D sdf;
+uniform vec4 color;
+uniform vec4 outline_color;
+uniform float outline;
+out vec4 out_color;
+void main() {
+    float d = texture(sdf, uv).a;
+    float w = max(fwidth(d) * 0.75, 0.001);
+    float fill = smoothstep(0.5 - w, 0.5 + w, d);
+    float edge = smoothstep(0.5 - outline - w, 0.5 - outline + w, d);
+    vec4 c = mix(outline_color, color, fill);
+    out_color = vec4(c.rgb, c.a * edge);
+}";
+
+
+
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Align{
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum VAlign{
+    ///The position is the top of the first line.
+    Top,
+    Center,
+    ///The position is the bottom of the last line.
+    Bottom,
+    ///The position is on the baseline of the first line.
+    Baseline,
+}
+
+#[derive(Copy,Clone,Debug)]
+pub struct TextStyle{
+    ///The height of a line.
+    pub size:f32,
+    pub color:[f32;4],
+    pub align:Align,
+    pub valign:VAlign,
+    ///The color and width of an outline, with the width a fraction of the size.
+    ///The width is limited to SdfFont::spread().
+    pub outline:Option<([f32;4],f32)>,
+}
+
+impl Default for TextStyle{
+    fn default()->TextStyle{
+        TextStyle{
+            size:16.0,
+            color:[1.0;4],
+            align:Align::Left,
+            valign:VAlign::Top,
+            outline:None
+        }
+    }
+}
+
+
+
+
+///Draws text from an SdfFont.
+pub struct TextProgram{
+    program:Program,
+    vbo:GLuint,
+    verts:Vec<[f32;4]>,
+}
+
+impl TextProgram{
+    pub fn new()->TextProgram{
+        Self::with_cache(None)
+    }
+
+    ///Same as new() but tries to load the linked program from the cache first.
+    pub fn new_cached(cache:&ProgramCache)->TextProgram{
+        Self::with_cache(Some(cache))
+    }
+
+    fn with_cache(cache:Option<&ProgramCache>)->TextProgram{
+        TextProgram{program:Program::new("TextProgram",VS_SRC,FS_SRC,cache),vbo:gen_buffer(),verts:Vec::new()}
+    }
+
+    //Rebuilds the program and its buffer if their context has since been lost.
+    fn restore(&mut self){
+        if self.program.restore(){
+            self.vbo=gen_buffer();
+        }
+    }
+
+    ///Draws over what is already in the viewport, with positions in world coordinates.
+    ///Start it after the other sessions of the frame are dropped.
+    pub fn new_world_session(&mut self,camera:&Camera2D,viewport:&Viewport)->TextSession{
+        self.restore();
+        let view=viewport.begin_keep(camera);
+        TextSession{a:self,view}
+    }
+
+    ///Draws over what is already in the viewport, with positions in physical pixels
+    ///from the top left of the viewport. For overlays that don't move with the camera.
+    pub fn new_hud_session(&mut self,viewport:&Viewport)->TextSession{
+        self.restore();
+        let (_,size)=viewport.inner();
+        let camera=Camera2D::from_rect(Rect::new(0.0,size.x,0.0,size.y));
+        let view=viewport.begin_keep(&camera);
+        TextSession{a:self,view}
+    }
+}
+
+impl Drop for TextProgram{
+    fn drop(&mut self){
+        context::delete(self.program.get_ctx(),&[Object::Buffer(self.vbo)]);
+    }
+}
+
+
+
+
+pub struct TextSession<'a>{
+    a:&'a mut TextProgram,
+    view:View,
+}
+
+impl<'a> Drop for TextSession<'a>{
+    fn drop(&mut self){
+        Viewport::end();
+    }
+}
+
+impl<'a> TextSession<'a>{
+    ///The view this session draws with.
+    pub fn view(&self)->&View{
+        &self.view
+    }
+
+    ///Draws text at a position in the session's coordinates.
+    ///The size is in the same units, so world text grows as the camera zooms in.
+    ///Lines are separated by '\n'.
+    pub fn draw_text(&mut self,font:&SdfFont,text:&str,pos:Vec2<f32>,style:&TextStyle){
+        let matrix=self.view.matrix;
+        self.draw(font,text,pos,style,matrix);
+    }
+
+    ///Draws text next to a world position, like a label attached to a circle.
+    ///The size is in physical pixels so labels stay readable at any zoom.
+    pub fn draw_label(&mut self,font:&SdfFont,text:&str,pos:Vec2<f32>,style:&TextStyle){
+        let pos=self.view.world_to_screen(pos);
+
+        //maps window pixels to the drawn area
+        let (o,s)=(self.view.offset,self.view.size);
+        let matrix=[
+            [2.0/s.x,0.0,0.0],
+            [0.0,-2.0/s.y,0.0],
+            [-1.0-2.0*o.x/s.x,1.0+2.0*o.y/s.y,1.0]
+        ];
+        self.draw(font,text,pos,style,matrix);
+    }
+
+    fn draw(&mut self,font:&SdfFont,text:&str,pos:Vec2<f32>,style:&TextStyle,matrix:[[f32;3];3]){
+        let size=style.size;
+        let height=font.measure(text,size).y;
+        let mut y=pos.y+match style.valign{
+            VAlign::Top=>0.0,
+            VAlign::Center=>-height/2.0,
+            VAlign::Bottom=>-height,
+            VAlign::Baseline=>-font.ascent()*size,
+        }+font.ascent()*size;
+
+        let verts=&mut self.a.verts;
+        verts.clear();
+        for line in text.split('\n'){
+            let width=font.line_width(line)*size;
+            let x=pos.x-match style.align{
+                Align::Left=>0.0,
+                Align::Center=>width/2.0,
+                Align::Right=>width,
+            };
+            font.layout_line(line,vec2(x,y),size,|a,b,r|{
+                let (u0,v0,u1,v1)=(r[0],r[1],r[0]+r[2],r[1]+r[3]);
+                verts.extend_from_slice(&[
+                    [a[0],a[1],u0,v0],[b[0],a[1],u1,v0],[a[0],b[1],u0,v1],
+                    [a[0],b[1],u0,v1],[b[0],a[1],u1,v0],[b[0],b[1],u1,v1]
+                ]);
+            });
+            y+=font.line_height()*size;
+        }
+        if verts.is_empty(){
+            return;
+        }
+
+        let (outline_color,outline)=match style.outline{
+            Some((color,width))=>(color,width.min(font.spread())/(2.0*font.spread())),
+            None=>(style.color,0.0)
+        };
+
+        let program=self.a.program.get_id();
+        BlendMode::Alpha.apply();
+        unsafe{
+            state::use_program(program);
+
+            state::set_uniform(program,"mmatrix",Uniform::Mat3(matrix));
+
+            state::set_uniform(program,"color",Uniform::Vec4(style.color));
+
+            state::set_uniform(program,"outline_color",Uniform::Vec4(outline_color));
+
+            state::set_uniform(program,"outline",Uniform::Float(outline));
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D,font.get_atlas().get_texture().get_id());
+            state::set_uniform(program,"sdf",Uniform::Int(0));
+            debug::check_error();
+
+            state::bind_array_buffer(self.a.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (verts.len()*mem::size_of::<[f32;4]>()) as GLsizeiptr,
+                verts.as_ptr() as *const std::ffi::c_void,
+                gl::STREAM_DRAW,
+            );
+
+            let stride=mem::size_of::<[f32;4]>() as i32;
+            let pos_attr = state::attrib_location(program,"position");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                2,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                stride,
+                ptr::null(),
+            );
+
+            let pos_attr = state::attrib_location(program,"texcoord");
+            gl::EnableVertexAttribArray(pos_attr as GLuint);
+            gl::VertexAttribPointer(
+                pos_attr as GLuint,
+                2,
+                gl::FLOAT,
+                gl::FALSE as GLboolean,
+                stride,
+                (2*mem::size_of::<f32>()) as *const std::ffi::c_void,
+            );
+
+            gl::DrawArrays(gl::TRIANGLES,0,verts.len() as i32);
+            stats::draw_call(verts.len());
+            debug::check_error();
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use axgeom::*;
+use crate::gl;
+use crate::debug;
+use crate::gl::types::*;
+use crate::context::{self,ContextId,Object};
+
+
+
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Filter{
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq,Eq)]
+pub enum Wrap{
+    ClampToEdge,
+    Repeat,
+    MirroredRepeat,
+}
+
+#[derive(Copy,Clone,Debug)]
+pub struct TextureOptions{
+    pub min_filter:Filter,
+    pub mag_filter:Filter,
+    pub wrap:Wrap,
+    ///Generate mipmaps and use them when minifying.
+    pub mipmaps:bool,
+    ///The texels are sRGB encoded, like most color images.
+    ///They are converted to linear when sampled, and nothing converts them back:
+    ///the window and offscreen targets aren't sRGB, so such textures draw too dark.
+    ///Only set it for shaders that encode their output themselves.
+    pub srgb:bool,
+}
+
+impl Default for TextureOptions{
+    fn default()->TextureOptions{
+        TextureOptions{
+            min_filter:Filter::Linear,
+            mag_filter:Filter::Linear,
+            wrap:Wrap::ClampToEdge,
+            mipmaps:false,
+            srgb:false
+        }
+    }
+}
+
+
+///An RGBA texture with eight bits per channel.
+///
+///Rows are given top row first, which matches both how images are stored
+///and how gl_PointCoord is oriented.
+pub struct Texture2D{
+    texture:GLuint,
+    dim:Vec2<usize>,
+    options:TextureOptions,
+    ctx:ContextId,
+}
+
+impl Texture2D{
+    ///data holds dim.x*dim.y RGBA texels.
+    pub fn from_rgba(dim:Vec2<usize>,data:&[u8],options:TextureOptions)->Texture2D{
+        assert_eq!(data.len(),dim.x*dim.y*4);
+
+        let mut texture=0;
+        unsafe{
+            gl::GenTextures(1,&mut texture);
+            gl::BindTexture(gl::TEXTURE_2D,texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT,1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                if options.srgb{gl::SRGB8_ALPHA8}else{gl::RGBA8} as GLint,
+                dim.x as GLsizei,
+                dim.y as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const std::ffi::c_void
+            );
+        }
+        let mut t=Texture2D{texture,dim,options,ctx:context::current()};
+        t.set_options(options);
+        t
+    }
+
+    pub fn get_id(&self)->u32{
+        self.texture
+    }
+
+    ///False once the context the texture was created in is lost.
+    ///Textures aren't recreated, there is no copy of their texels to upload again.
+    pub fn is_valid(&self)->bool{
+        !context::is_lost(self.ctx)
+    }
+
+    pub fn get_dim(&self)->Vec2<usize>{
+        self.dim
+    }
+
+    pub fn get_options(&self)->TextureOptions{
+        self.options
+    }
+
+    ///The srgb field is fixed when the texture is created and is ignored here.
+    pub fn set_options(&mut self,options:TextureOptions){
+        let options=TextureOptions{srgb:self.options.srgb,..options};
+        let filter=|f:Filter,mip:bool|->GLenum{
+            match (f,mip){
+                (Filter::Nearest,false)=>gl::NEAREST,
+                (Filter::Linear,false)=>gl::LINEAR,
+                (Filter::Nearest,true)=>gl::NEAREST_MIPMAP_NEAREST,
+                (Filter::Linear,true)=>gl::LINEAR_MIPMAP_LINEAR,
+            }
+        };
+        let wrap=match options.wrap{
+            Wrap::ClampToEdge=>gl::CLAMP_TO_EDGE,
+            Wrap::Repeat=>gl::REPEAT,
+            Wrap::MirroredRepeat=>gl::MIRRORED_REPEAT,
+        };
+
+        unsafe{
+            gl::BindTexture(gl::TEXTURE_2D,self.texture);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MIN_FILTER,filter(options.min_filter,options.mipmaps) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_MAG_FILTER,filter(options.mag_filter,false) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_WRAP_S,wrap as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D,gl::TEXTURE_WRAP_T,wrap as GLint);
+            if options.mipmaps{
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+        debug::check_error();
+        self.options=options;
+    }
+
+    ///Overwrites part of the texture. data holds size.x*size.y RGBA texels.
+    ///Mipmaps are regenerated if enabled.
+    pub fn update_sub_region(&mut self,offset:Vec2<usize>,size:Vec2<usize>,data:&[u8]){
+        assert!(offset.x+size.x<=self.dim.x && offset.y+size.y<=self.dim.y);
+        assert_eq!(data.len(),size.x*size.y*4);
+
+        unsafe{
+            gl::BindTexture(gl::TEXTURE_2D,self.texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT,1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                offset.x as GLint,
+                offset.y as GLint,
+                size.x as GLsizei,
+                size.y as GLsizei,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as *const std::ffi::c_void
+            );
+            if self.options.mipmaps{
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+        }
+        debug::check_error();
+    }
+}
+
+impl Drop for Texture2D{
+    fn drop(&mut self){
+        context::delete(self.ctx,&[Object::Texture(self.texture)]);
+    }
+}
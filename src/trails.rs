@@ -0,0 +1,90 @@
+use axgeom::*;
+use crate::offscreen::OffscreenTarget;
+use crate::postprocess::{FullscreenPass,Uniform};
+use crate::blit_program::BlitProgram;
+use crate::viewport::Viewport;
+use crate::blend::BlendMode;
+
+
+static FADE_FS_SRC: &'static str = "
+#version 300 es
+precision mediump float;
+in vec2 uv;
+uniform vec4 color;
+out vec4 out_color;
+void main() {
+    out_color = color;
+}";
+
+
+
+
+///A persistent offscreen target for drawing particles with fading trails.
+///
+///Instead of being cleared, every frame the background color is blended
+///over the previous frame, see CircleProgram::new_trails_session().
+///Afterwards present() draws the result into a viewport.
+///
+///A half float target is used when supported, since with eight bits
+///per channel faint trails never quite fade to the background.
+pub struct Trails{
+    target:OffscreenTarget,
+    fade:FullscreenPass,
+    blit:BlitProgram,
+    ///What trails fade towards.
+    pub back_color:[f32;3],
+    ///How much of the previous frame fades away every frame, from 0 to 1.
+    ///One is the same as clearing.
+    pub decay:f32,
+    //whether the next frame has to start from a clear target
+    needs_clear:bool,
+}
+
+impl Trails{
+    ///dim should be the pixel size of the viewport the trails are presented in.
+    pub fn new(dim:Vec2<usize>,back_color:[f32;3],decay:f32)->Trails{
+        Trails{
+            target:OffscreenTarget::new_float(dim),
+            fade:FullscreenPass::new(FADE_FS_SRC),
+            blit:BlitProgram::new(),
+            back_color,
+            decay,
+            needs_clear:true
+        }
+    }
+
+    ///Resizing throws away the existing trails.
+    ///Also recreates the target if the context was lost.
+    pub fn resize(&mut self,dim:Vec2<usize>){
+        if dim!=self.target.get_dim() || !self.target.is_valid(){
+            self.target.resize(dim);
+            self.needs_clear=true;
+        }
+    }
+
+    ///Throws away the existing trails.
+    pub fn clear(&mut self){
+        self.needs_clear=true;
+    }
+
+    //Fades the previous frame and returns the viewport to keep drawing into.
+    pub(crate) fn begin_frame(&mut self)->Viewport{
+        let viewport=self.target.viewport();
+        let alpha=if self.needs_clear{1.0}else{self.decay.clamp(0.0,1.0)};
+        self.needs_clear=false;
+
+        let c=self.back_color;
+        viewport.begin_inner();
+        BlendMode::Alpha.apply();
+        self.fade.draw_current(&[],&[
+            ("color",Uniform::Vec4([c[0],c[1],c[2],alpha]))
+        ]);
+        Viewport::end();
+        viewport
+    }
+
+    ///Draws the trails into the viewport.
+    pub fn present(&mut self,viewport:&Viewport){
+        self.blit.draw(viewport,&self.target,1.0,BlendMode::None);
+    }
+}
@@ -3,31 +3,46 @@
 
 use crate::gl;
 use crate::gl::types::*;
+use crate::stats;
+use crate::state;
+use crate::debug;
+use crate::context::{self,ContextId,Object};
 
 
 
 
 use core::mem;
+use std::cell::Cell;
 
-#[derive(Clone,Debug)]
+///A vertex buffer object with a copy of its vertices on the cpu.
+///It can't be cloned, as both copies would delete the same buffer.
+///
+///If the context is lost the copy is uploaded again the next time the buffer is used.
+#[derive(Debug)]
 pub struct Buffer<V>{
-    vbo:u32,
+    //both change when the buffer is uploaded into a new context
+    vbo:Cell<u32>,
+    ctx:Cell<ContextId>,
     buffer:Vec<V>
 }
 
 impl<V> Drop for Buffer<V>{
     fn drop(&mut self){
-        //TODO make sure this is ok to do
-        unsafe{
-            gl::DeleteBuffers(1, &self.vbo);
-        }
+        log::trace!(target:"gl::lifecycle",buffer=self.vbo.get();"dropping buffer");
+        context::delete(self.ctx.get(),&[Object::Buffer(self.vbo.get())]);
     }
 }
 
 
 impl<V:Default> Buffer<V>{
     pub fn get_id(&self)->u32{
-        self.vbo
+        if context::is_stale(self.ctx.get()){
+            let vbo=generate(&self.buffer);
+            log::debug!(target:"gl::lifecycle",old=self.vbo.get(),buffer=vbo,vertices=self.buffer.len();"uploaded buffer into a new context");
+            self.vbo.set(vbo);
+            self.ctx.set(context::current());
+        }
+        self.vbo.get()
     }
     pub fn get_verts_mut(&mut self)->&mut [V]{
         &mut self.buffer
@@ -35,10 +50,11 @@ impl<V:Default> Buffer<V>{
 
     
     pub fn update(&mut self){
-        let vbo=&mut self.vbo;
+        stats::upload(self.buffer.len());
+        let vbo=self.get_id();
         
         unsafe{
-            gl::BindBuffer(gl::ARRAY_BUFFER, *vbo);
+            state::bind_array_buffer(vbo);
             
             gl::BufferSubData(
                 gl::ARRAY_BUFFER,
@@ -47,7 +63,7 @@ impl<V:Default> Buffer<V>{
                 mem::transmute(self.buffer.as_ptr()),
             );
         }
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);   
+        debug::check_error();   
     }
     
 
@@ -59,9 +75,9 @@ impl<V:Default> Buffer<V>{
     pub fn re_generate_buffer(&mut self,num_verticies:usize){
         
         self.buffer.resize_with(num_verticies,Default::default);
-        let vbo=&mut self.vbo;
+        let vbo=self.get_id();
         unsafe{
-            gl::BindBuffer(gl::ARRAY_BUFFER, *vbo);
+            state::bind_array_buffer(vbo);
             gl::BufferData(
                 gl::ARRAY_BUFFER,
                 (self.buffer.len() *mem::size_of::<V>()) as GLsizeiptr,
@@ -69,31 +85,43 @@ impl<V:Default> Buffer<V>{
                 gl::DYNAMIC_DRAW,
             );
         }
-        assert_eq!(unsafe{gl::GetError()},gl::NO_ERROR);
-        
+        debug::check_error();
+        log::debug!(target:"gl::lifecycle",buffer=vbo,vertices=num_verticies,bytes=num_verticies*mem::size_of::<V>();"resized buffer");
     }
 
     pub fn create_vbo(num_verticies:usize)->Buffer<V>{
-        let mut vbo = 0;
-        
         let mut buffer=Vec::new();
         buffer.resize_with(num_verticies,Default::default);
         
-        unsafe {
+        let vbo=generate(&buffer);
+        log::debug!(target:"gl::lifecycle",buffer=vbo,ty=std::any::type_name::<V>(),vertices=num_verticies,bytes=num_verticies*mem::size_of::<V>();"created buffer");
 
-            // Create a Vertex Buffer Object and copy the vertex data to it
-            gl::GenBuffers(1, &mut vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (buffer.len() *mem::size_of::<V>()) as GLsizeiptr,
-                mem::transmute(buffer.as_ptr()),
-                gl::DYNAMIC_DRAW,
-            );
+        Buffer{vbo:Cell::new(vbo),ctx:Cell::new(context::current()),buffer}
+    }
+}
 
-            
-        }
+//Creates a vertex buffer object and copies the vertices to it.
+fn generate<V>(buffer:&[V])->u32{
+    let mut vbo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        state::bind_array_buffer(vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (buffer.len() *mem::size_of::<V>()) as GLsizeiptr,
+            mem::transmute(buffer.as_ptr()),
+            gl::DYNAMIC_DRAW,
+        );
+    }
+    debug::label(gl::BUFFER_KHR,vbo,&format!("Buffer<{}>",std::any::type_name::<V>()));
+    vbo
+}
 
-        Buffer{vbo,buffer}
+//An empty buffer, for vertices that are streamed in every draw.
+pub(crate) fn gen_buffer()->GLuint{
+    let mut vbo=0;
+    unsafe{
+        gl::GenBuffers(1,&mut vbo);
     }
-}
\ No newline at end of file
+    vbo
+}
@@ -0,0 +1,141 @@
+use axgeom::*;
+use crate::gl;
+use crate::gl::types::*;
+use crate::camera::*;
+
+
+
+///A rectangle of the window that a draw session renders into.
+///
+///Several sessions with different cameras can draw into different viewports
+///of the same frame. Everything is in physical pixels with the origin at the
+///top left of the window, or of the OffscreenTarget the viewport came from.
+#[derive(Copy,Clone,Debug)]
+pub struct Viewport{
+    ///Top left corner of the viewport.
+    pub offset:Vec2<usize>,
+    pub size:Vec2<usize>,
+    ///The size of the whole window or target, see GlSys::get_physical_dim().
+    pub target_dim:Vec2<usize>,
+    ///The color and thickness in pixels of a border drawn along the inside edge.
+    pub border:Option<([f32;3],usize)>,
+    //zero is the window
+    pub(crate) framebuffer:GLuint,
+}
+
+impl Viewport{
+    ///A viewport covering the whole window.
+    pub fn full(dim:Vec2<usize>)->Viewport{
+        Viewport{offset:vec2(0,0),size:dim,target_dim:dim,border:None,framebuffer:0}
+    }
+
+    ///A viewport covering part of the window.
+    pub fn sub(dim:Vec2<usize>,offset:Vec2<usize>,size:Vec2<usize>)->Viewport{
+        Viewport{offset,size,target_dim:dim,border:None,framebuffer:0}
+    }
+
+    pub fn with_border(mut self,color:[f32;3],thickness:usize)->Viewport{
+        self.border=Some((color,thickness));
+        self
+    }
+
+    //The area inside the border.
+    pub(crate) fn inner(&self)->(Vec2<f32>,Vec2<f32>){
+        let t=self.border.map(|(_,t)|t as f32).unwrap_or(0.0);
+        let offset=vec2(self.offset.x as f32+t,self.offset.y as f32+t);
+        let size=vec2((self.size.x as f32-2.0*t).max(0.0),(self.size.y as f32-2.0*t).max(0.0));
+        (offset,size)
+    }
+
+    ///Fits the camera into the area inside the border.
+    ///The returned view is relative to the whole window.
+    pub fn view(&self,camera:&Camera2D)->View{
+        let (offset,size)=self.inner();
+        let mut view=camera.view(vec2(size.x.round() as usize,size.y.round() as usize));
+        view.offset.x+=offset.x;
+        view.offset.y+=offset.y;
+        view
+    }
+
+    //Converts an area relative to the window to what glViewport and glScissor expect.
+    //gl has its origin at the bottom left.
+    fn gl_rect(&self,offset:Vec2<f32>,size:Vec2<f32>)->(GLint,GLint,GLsizei,GLsizei){
+        let x=offset.x.round() as GLint;
+        let y=(self.target_dim.y as f32-offset.y-size.y).round() as GLint;
+        (x,y,size.x.round() as GLsizei,size.y.round() as GLsizei)
+    }
+
+    //Binds the framebuffer, draws the border and leaves the scissor enabled.
+    fn begin_border(&self){
+        unsafe{
+            gl::BindFramebuffer(gl::FRAMEBUFFER,self.framebuffer);
+            gl::Enable(gl::SCISSOR_TEST);
+
+            if let Some((color,_))=self.border{
+                let (x,y,w,h)=self.gl_rect(
+                    vec2(self.offset.x as f32,self.offset.y as f32),
+                    vec2(self.size.x as f32,self.size.y as f32)
+                );
+                gl::Scissor(x,y,w,h);
+                gl::ClearColor(color[0], color[1], color[2], 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+        }
+    }
+
+    //Clears the border, letterbox bars and background,
+    //and leaves the gl viewport and scissor set to the view.
+    pub(crate) fn begin(&self,camera:&Camera2D,back_color:[f32;3])->View{
+        self.begin_with(camera,Some(back_color))
+    }
+
+    //Same as begin() but keeps what was drawn into the view before.
+    pub(crate) fn begin_keep(&self,camera:&Camera2D)->View{
+        self.begin_with(camera,None)
+    }
+
+    fn begin_with(&self,camera:&Camera2D,back_color:Option<[f32;3]>)->View{
+        let view=self.view(camera);
+        self.begin_border();
+
+        unsafe{
+            if let FitMode::Letterbox(bar_color)=camera.fit{
+                let (offset,size)=self.inner();
+                let (x,y,w,h)=self.gl_rect(offset,size);
+                gl::Scissor(x,y,w,h);
+                gl::ClearColor(bar_color[0], bar_color[1], bar_color[2], 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+
+            let (x,y,w,h)=self.gl_rect(view.offset,view.size);
+            gl::Scissor(x,y,w,h);
+            gl::Viewport(x,y,w,h);
+            if let Some(back_color)=back_color{
+                gl::ClearColor(back_color[0], back_color[1], back_color[2], 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+        }
+        view
+    }
+
+    //Draws the border and leaves the gl viewport and scissor set
+    //to the area inside it, without clearing it.
+    pub(crate) fn begin_inner(&self){
+        self.begin_border();
+        let (offset,size)=self.inner();
+        let (x,y,w,h)=self.gl_rect(offset,size);
+        unsafe{
+            gl::Scissor(x,y,w,h);
+            gl::Viewport(x,y,w,h);
+        }
+    }
+
+    //Called when a session ends so the scissor and framebuffer
+    //don't leak into other drawing.
+    pub(crate) fn end(){
+        unsafe{
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER,0);
+        }
+    }
+}